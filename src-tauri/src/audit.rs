@@ -0,0 +1,164 @@
+//! Tamper-evident trail of sensitive actions, kept in the primary database.
+//!
+//! Each record stores the SHA-256 of its own contents together with the
+//! previous record's hash, so editing, deleting or reordering rows breaks the
+//! chain. Triggers reject UPDATE and DELETE; the chain catches changes made
+//! by tools that drop the triggers first. The first append also records in
+//! `audit_chain` that a trail exists, so dropping `audit_log` shows up too.
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::db;
+
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ts_ms INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    prev_hash TEXT NOT NULL,
+    hash TEXT NOT NULL
+);
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+CREATE TABLE IF NOT EXISTS audit_chain (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    started_ms INTEGER NOT NULL
+);
+CREATE TRIGGER IF NOT EXISTS audit_chain_no_update BEFORE UPDATE ON audit_chain
+BEGIN SELECT RAISE(ABORT, 'audit_chain is append-only'); END;
+CREATE TRIGGER IF NOT EXISTS audit_chain_no_delete BEFORE DELETE ON audit_chain
+BEGIN SELECT RAISE(ABORT, 'audit_chain is append-only'); END;
+";
+
+/// Hash over every column, encoded as a JSON array so field boundaries are
+/// unambiguous.
+fn record_hash(id: i64, ts_ms: i64, action: &str, detail: &str, prev_hash: &str) -> String {
+    let encoded = serde_json::json!([id, ts_ms, action, detail, prev_hash]).to_string();
+    format!("{:x}", Sha256::digest(encoded.as_bytes()))
+}
+
+fn append(db_path: &Path, action: &str, detail: &serde_json::Value) -> Result<(), String> {
+    if action.trim().is_empty() {
+        return Err("Audit action must not be empty".to_string());
+    }
+    let detail = detail.to_string();
+    let mut conn = db::open(db_path)?;
+    conn.execute_batch(SCHEMA).map_err(|err| err.to_string())?;
+
+    // IMMEDIATE so two appends can't both chain onto the same last record.
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|err| err.to_string())?;
+    let prev_hash: String = tx
+        .query_row(
+            "SELECT hash FROM audit_log ORDER BY id DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|err| err.to_string())?
+        .unwrap_or_else(|| GENESIS_HASH.to_string());
+    let id: i64 = tx
+        .query_row(
+            "SELECT COALESCE((SELECT seq FROM sqlite_sequence WHERE name = 'audit_log'), 0) + 1",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|err| err.to_string())?;
+    let ts_ms = chrono::Utc::now().timestamp_millis();
+    tx.execute(
+        "INSERT OR IGNORE INTO audit_chain (id, started_ms) VALUES (1, ?1)",
+        [ts_ms],
+    )
+    .map_err(|err| err.to_string())?;
+    let hash = record_hash(id, ts_ms, action, &detail, &prev_hash);
+    tx.execute(
+        "INSERT INTO audit_log (id, ts_ms, action, detail, prev_hash, hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, ts_ms, action, detail, prev_hash, hash],
+    )
+    .map_err(|err| err.to_string())?;
+    tx.commit().map_err(|err| err.to_string())
+}
+
+fn table_exists(conn: &Connection, name: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        [name],
+        |row| row.get(0),
+    )
+    .map_err(|err| err.to_string())
+}
+
+fn verify(conn: &Connection) -> Result<bool, String> {
+    if !table_exists(conn, "audit_log")? {
+        // Intact only if no record was ever appended.
+        return Ok(!table_exists(conn, "audit_chain")?);
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT id, ts_ms, action, detail, prev_hash, hash FROM audit_log ORDER BY id")
+        .map_err(|err| err.to_string())?;
+    let mut rows = stmt.query([]).map_err(|err| err.to_string())?;
+    let mut expected_id = 1i64;
+    let mut prev = GENESIS_HASH.to_string();
+    while let Some(row) = rows.next().map_err(|err| err.to_string())? {
+        let read = |index| row.get_ref(index).map_err(|err| err.to_string());
+        let id = read(0)?.as_i64().map_err(|err| err.to_string())?;
+        let ts_ms = read(1)?.as_i64().map_err(|err| err.to_string())?;
+        let action = read(2)?.as_str().map_err(|err| err.to_string())?;
+        let detail = read(3)?.as_str().map_err(|err| err.to_string())?;
+        let prev_hash = read(4)?.as_str().map_err(|err| err.to_string())?;
+        let hash = read(5)?.as_str().map_err(|err| err.to_string())?;
+        // A gap in ids is a deleted record even if the rest re-chains.
+        if id != expected_id
+            || prev_hash != prev
+            || hash != record_hash(id, ts_ms, action, detail, prev_hash)
+        {
+            return Ok(false);
+        }
+        prev = hash.to_string();
+        expected_id += 1;
+    }
+
+    // AUTOINCREMENT remembers the highest id ever used, so deleting the
+    // newest records shows up as well.
+    let last_issued: i64 = conn
+        .query_row(
+            "SELECT COALESCE((SELECT seq FROM sqlite_sequence WHERE name = 'audit_log'), 0)",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|err| err.to_string())?;
+    Ok(last_issued == expected_id - 1)
+}
+
+/// Appends `action` with its JSON `detail` to the primary database's audit log.
+#[tauri::command]
+pub async fn audit_log(
+    app: AppHandle,
+    action: String,
+    detail: serde_json::Value,
+) -> Result<(), String> {
+    let db_path = db::primary_db_path(&app)?;
+    db::run_blocking(move || append(&db_path, &action, &detail)).await
+}
+
+/// Recomputes the hash chain of the primary database's audit log (the one
+/// `audit_log` appends to) and reports whether every record is intact. A
+/// database that never had an audit record counts as intact; one whose log
+/// was dropped does not.
+#[tauri::command]
+pub async fn verify_audit_chain(app: AppHandle) -> Result<bool, String> {
+    let db_path = db::primary_db_path(&app)?;
+    db::run_blocking(move || verify(&db::open_readonly(&db_path)?)).await
+}
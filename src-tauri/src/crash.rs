@@ -0,0 +1,51 @@
+//! Crash reports written from the panic hook so users have something to send us.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Set once the app's log directory is known (in `setup`). Panics before that
+/// point are only logged.
+static REPORT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_report_dir(dir: PathBuf) {
+    let _ = REPORT_DIR.set(dir);
+}
+
+/// Writes `crash-<timestamp>.txt` into the log directory. Every failure is
+/// swallowed: this runs inside the panic hook and must never panic itself.
+pub fn write_report(message: &str, payload: Option<&str>) {
+    let Some(dir) = REPORT_DIR.get() else {
+        return;
+    };
+
+    let now = chrono::Utc::now();
+    let mut report = String::new();
+    let _ = writeln!(report, "JurisDesk crash report");
+    let _ = writeln!(report, "time: {}", now.to_rfc3339());
+    let _ = writeln!(report, "version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        report,
+        "os: {} ({})",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(report, "message: {}", message);
+    let _ = writeln!(
+        report,
+        "payload: {}",
+        payload.unwrap_or("<non-string payload>")
+    );
+
+    // `capture` honours RUST_BACKTRACE / RUST_LIB_BACKTRACE.
+    let backtrace = std::backtrace::Backtrace::capture();
+    if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+        let _ = writeln!(report, "\nbacktrace:\n{}", backtrace);
+    }
+
+    // Colons aren't allowed in Windows file names.
+    let file_name = format!("crash-{}.txt", now.format("%Y-%m-%dT%H-%M-%S%.3fZ"));
+    if std::fs::create_dir_all(dir).is_ok() {
+        let _ = std::fs::write(dir.join(file_name), report);
+    }
+}
@@ -0,0 +1,130 @@
+//! Checks an API key against the provider before the user saves it.
+//!
+//! Only the providers JurisDesk integrates with can be reached, identified by
+//! name rather than URL, so the webview can't use this to make the app call
+//! arbitrary hosts.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::ratelimit::{self, RateLimiter};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Cheap authenticated endpoints that list models and cost nothing.
+struct Provider {
+    url: &'static str,
+    auth_header: &'static str,
+    auth_prefix: &'static str,
+    extra_headers: &'static [(&'static str, &'static str)],
+}
+
+fn provider(name: &str) -> Option<Provider> {
+    match name {
+        "claude" => Some(Provider {
+            url: "https://api.anthropic.com/v1/models",
+            auth_header: "x-api-key",
+            auth_prefix: "",
+            extra_headers: &[("anthropic-version", "2023-06-01")],
+        }),
+        "openai" => Some(Provider {
+            url: "https://api.openai.com/v1/models",
+            auth_header: "Authorization",
+            auth_prefix: "Bearer ",
+            extra_headers: &[],
+        }),
+        "gemini" => Some(Provider {
+            url: "https://generativelanguage.googleapis.com/v1beta/models",
+            auth_header: "x-goog-api-key",
+            auth_prefix: "",
+            extra_headers: &[],
+        }),
+        _ => None,
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialTestOutcome {
+    Ok,
+    /// The provider answered 401/403: the key is wrong or revoked.
+    AuthFailed,
+    /// The provider answered with some other non-success status.
+    HttpError,
+    /// No response at all (offline, DNS, TLS, timeout).
+    NetworkError,
+}
+
+#[derive(Serialize)]
+pub struct CredentialTestResult {
+    pub outcome: CredentialTestOutcome,
+    pub status: Option<u16>,
+    pub authenticated: bool,
+    pub message: Option<String>,
+}
+
+/// Makes one authenticated request to `endpoint` (`"claude"`, `"openai"` or
+/// `"gemini"`) with `key` and reports how it went. Nothing is stored.
+/// Rate limited by [`ratelimit::CREDENTIAL_TEST`].
+#[tauri::command]
+pub async fn test_api_credential(
+    limiter: State<'_, RateLimiter>,
+    endpoint: String,
+    key: String,
+) -> Result<CredentialTestResult, CommandError> {
+    let provider = provider(&endpoint).ok_or_else(|| {
+        CommandError::InvalidInput(format!(
+            "Unknown endpoint '{}'; expected claude, openai or gemini",
+            endpoint
+        ))
+    })?;
+    if key.trim().is_empty() {
+        return Err(CommandError::InvalidInput(
+            "API key must not be empty".to_string(),
+        ));
+    }
+    limiter.check(&ratelimit::CREDENTIAL_TEST)?;
+
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|err| CommandError::Backend(err.to_string()))?;
+    let mut request = client.get(provider.url).header(
+        provider.auth_header,
+        format!("{}{}", provider.auth_prefix, key.trim()),
+    );
+    for (name, value) in provider.extra_headers {
+        request = request.header(*name, *value);
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(err) => {
+            return Ok(CredentialTestResult {
+                outcome: CredentialTestOutcome::NetworkError,
+                status: None,
+                authenticated: false,
+                message: Some(err.without_url().to_string()),
+            })
+        }
+    };
+
+    let status = response.status();
+    let outcome = if status.is_success() {
+        CredentialTestOutcome::Ok
+    } else if matches!(status.as_u16(), 401 | 403) {
+        CredentialTestOutcome::AuthFailed
+    } else {
+        CredentialTestOutcome::HttpError
+    };
+    Ok(CredentialTestResult {
+        authenticated: status.is_success(),
+        outcome,
+        status: Some(status.as_u16()),
+        message: status.canonical_reason().map(String::from),
+    })
+}
@@ -0,0 +1,399 @@
+//! Passphrase-based encryption for files that leave the app (backups, bundles).
+//!
+//! Layout: `magic (4) | version (1) | salt (16) | nonce (12) | check (16) | ciphertext`.
+//! Argon2id derives both the AES-256-GCM key and the `check` value from the
+//! passphrase, so a wrong passphrase can be told apart from a damaged file.
+//! The whole header is authenticated as associated data.
+//!
+//! [`SealWriter`] and [`OpenReader`] handle files too large to hold in
+//! memory. The header is `magic | version | salt | check`, followed by
+//! records of `nonce | ciphertext`, each encrypting `STREAM_CHUNK` bytes
+//! (the last one fewer). Every record authenticates the header, its index
+//! and whether it is the last one, so records can't be reordered, dropped or
+//! truncated away unnoticed.
+//!
+//! The raw-key helpers at the bottom are for data that never leaves the
+//! machine and is encrypted under an app-managed key instead of a passphrase.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+
+const VERSION: u8 = 1;
+const MAGIC_LEN: usize = 4;
+pub const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const CHECK_LEN: usize = 16;
+pub const KEY_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC_LEN + 1 + SALT_LEN + NONCE_LEN + CHECK_LEN;
+
+#[derive(Debug)]
+pub enum OpenError {
+    WrongPassphrase,
+    Corrupt(String),
+}
+
+impl std::fmt::Display for OpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpenError::WrongPassphrase => write!(f, "Wrong passphrase"),
+            OpenError::Corrupt(reason) => write!(f, "File is corrupt: {}", reason),
+        }
+    }
+}
+
+fn derive(passphrase: &str, salt: &[u8]) -> Result<(Key<Aes256Gcm>, [u8; CHECK_LEN]), String> {
+    let mut output = [0u8; KEY_LEN + CHECK_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut output)
+        .map_err(|err| err.to_string())?;
+    let key = *Key::<Aes256Gcm>::from_slice(&output[..KEY_LEN]);
+    let mut check = [0u8; CHECK_LEN];
+    check.copy_from_slice(&output[KEY_LEN..]);
+    Ok((key, check))
+}
+
+/// Encrypts `plaintext` in memory and returns the complete file contents.
+pub fn seal(
+    magic: &[u8; MAGIC_LEN],
+    passphrase: &str,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".to_string());
+    }
+
+    let salt: [u8; SALT_LEN] = random_bytes();
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let (key, check) = derive(passphrase, &salt)?;
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    sealed.extend_from_slice(magic);
+    sealed.push(VERSION);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&check);
+
+    let ciphertext = Aes256Gcm::new(&key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &sealed,
+            },
+        )
+        .map_err(|_| "Encryption failed".to_string())?;
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypts data produced by [`seal`] with the same `magic`.
+pub fn open(magic: &[u8; MAGIC_LEN], passphrase: &str, data: &[u8]) -> Result<Vec<u8>, OpenError> {
+    if data.len() < HEADER_LEN || &data[..MAGIC_LEN] != magic {
+        return Err(OpenError::Corrupt("unrecognized file format".to_string()));
+    }
+    if data[MAGIC_LEN] != VERSION {
+        return Err(OpenError::Corrupt(format!(
+            "unsupported format version {}",
+            data[MAGIC_LEN]
+        )));
+    }
+
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let salt_start = MAGIC_LEN + 1;
+    let nonce_start = salt_start + SALT_LEN;
+    let check_start = nonce_start + NONCE_LEN;
+    let salt = &header[salt_start..nonce_start];
+    let nonce = Nonce::from_slice(&header[nonce_start..check_start]);
+
+    let (key, check) = derive(passphrase, salt).map_err(OpenError::Corrupt)?;
+    if check[..] != header[check_start..] {
+        return Err(OpenError::WrongPassphrase);
+    }
+
+    Aes256Gcm::new(&key)
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| OpenError::Corrupt("authentication failed".to_string()))
+}
+
+const STREAM_HEADER_LEN: usize = MAGIC_LEN + 1 + SALT_LEN + CHECK_LEN;
+const STREAM_CHUNK: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const STREAM_RECORD_LEN: usize = NONCE_LEN + STREAM_CHUNK + TAG_LEN;
+
+fn record_aad(header: &[u8], index: u64, last: bool) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(header.len() + 9);
+    aad.extend_from_slice(header);
+    aad.extend_from_slice(&index.to_be_bytes());
+    aad.push(u8::from(last));
+    aad
+}
+
+/// Encrypts everything written to it, one record per `STREAM_CHUNK` bytes.
+/// [`SealWriter::finish`] must be called to write the final record; without
+/// it the output can't be opened.
+pub struct SealWriter<W: Write> {
+    inner: W,
+    key: Key<Aes256Gcm>,
+    header: Vec<u8>,
+    buffer: Vec<u8>,
+    index: u64,
+}
+
+impl<W: Write> SealWriter<W> {
+    pub fn new(mut inner: W, magic: &[u8; MAGIC_LEN], passphrase: &str) -> Result<Self, String> {
+        if passphrase.is_empty() {
+            return Err("Passphrase must not be empty".to_string());
+        }
+        let salt: [u8; SALT_LEN] = random_bytes();
+        let (key, check) = derive(passphrase, &salt)?;
+        let mut header = Vec::with_capacity(STREAM_HEADER_LEN);
+        header.extend_from_slice(magic);
+        header.push(VERSION);
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&check);
+        inner.write_all(&header).map_err(|err| err.to_string())?;
+        Ok(Self {
+            inner,
+            key,
+            header,
+            buffer: Vec::with_capacity(STREAM_CHUNK),
+            index: 0,
+        })
+    }
+
+    fn write_record(&mut self, last: bool) -> io::Result<()> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = record_aad(&self.header, self.index, last);
+        let ciphertext = Aes256Gcm::new(&self.key)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &self.buffer,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| io::Error::other("Encryption failed"))?;
+        self.inner.write_all(&nonce)?;
+        self.inner.write_all(&ciphertext)?;
+        self.buffer.clear();
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Writes the final record and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_record(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for SealWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // A full chunk is only written once more data shows it isn't the last.
+        if self.buffer.len() == STREAM_CHUNK {
+            self.write_record(false)?;
+        }
+        let taken = buf.len().min(STREAM_CHUNK - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..taken]);
+        Ok(taken)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts a [`SealWriter`] file on demand, one record at a time, with
+/// random access so it can back a zip reader.
+pub struct OpenReader<R: Read + Seek> {
+    inner: R,
+    key: Key<Aes256Gcm>,
+    header: Vec<u8>,
+    records: u64,
+    len: u64,
+    pos: u64,
+    /// Index and plaintext of the most recently decrypted record.
+    current: Option<(u64, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> OpenReader<R> {
+    pub fn new(mut inner: R, magic: &[u8; MAGIC_LEN], passphrase: &str) -> Result<Self, OpenError> {
+        let corrupt = |reason: &str| OpenError::Corrupt(reason.to_string());
+        let mut header = vec![0u8; STREAM_HEADER_LEN];
+        inner
+            .read_exact(&mut header)
+            .map_err(|_| corrupt("unrecognized file format"))?;
+        if &header[..MAGIC_LEN] != magic {
+            return Err(corrupt("unrecognized file format"));
+        }
+        if header[MAGIC_LEN] != VERSION {
+            return Err(OpenError::Corrupt(format!(
+                "unsupported format version {}",
+                header[MAGIC_LEN]
+            )));
+        }
+        let salt_start = MAGIC_LEN + 1;
+        let check_start = salt_start + SALT_LEN;
+        let (key, check) =
+            derive(passphrase, &header[salt_start..check_start]).map_err(OpenError::Corrupt)?;
+        if check[..] != header[check_start..] {
+            return Err(OpenError::WrongPassphrase);
+        }
+
+        let file_len = inner
+            .seek(SeekFrom::End(0))
+            .map_err(|err| OpenError::Corrupt(err.to_string()))?;
+        let body = file_len.saturating_sub(STREAM_HEADER_LEN as u64);
+        let record_len = STREAM_RECORD_LEN as u64;
+        let records = body.div_ceil(record_len).max(1);
+        let last_len = body - (records - 1) * record_len;
+        let overhead = (NONCE_LEN + TAG_LEN) as u64;
+        if last_len < overhead {
+            return Err(corrupt("truncated"));
+        }
+        Ok(Self {
+            inner,
+            key,
+            header,
+            records,
+            len: (records - 1) * STREAM_CHUNK as u64 + (last_len - overhead),
+            pos: 0,
+            current: None,
+        })
+    }
+
+    fn load(&mut self, index: u64) -> io::Result<&[u8]> {
+        if self.current.as_ref().map(|(loaded, _)| *loaded) != Some(index) {
+            let last = index + 1 == self.records;
+            let offset = STREAM_HEADER_LEN as u64 + index * STREAM_RECORD_LEN as u64;
+            self.inner.seek(SeekFrom::Start(offset))?;
+            let mut record = Vec::with_capacity(STREAM_RECORD_LEN);
+            (&mut self.inner)
+                .take(STREAM_RECORD_LEN as u64)
+                .read_to_end(&mut record)?;
+            if record.len() < NONCE_LEN + TAG_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "File is truncated",
+                ));
+            }
+            let (nonce, ciphertext) = record.split_at(NONCE_LEN);
+            let aad = record_aad(&self.header, index, last);
+            let plaintext = Aes256Gcm::new(&self.key)
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: &aad,
+                    },
+                )
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "File is corrupt: authentication failed",
+                    )
+                })?;
+            self.current = Some((index, plaintext));
+        }
+        Ok(self
+            .current
+            .as_ref()
+            .map(|(_, plaintext)| plaintext.as_slice())
+            .unwrap_or(&[]))
+    }
+}
+
+impl<R: Read + Seek> Read for OpenReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let chunk = STREAM_CHUNK as u64;
+        let index = self.pos / chunk;
+        let offset = (self.pos % chunk) as usize;
+        let plaintext = self.load(index)?;
+        let available = plaintext.len().saturating_sub(offset);
+        let count = available.min(buf.len());
+        buf[..count].copy_from_slice(&plaintext[offset..offset + count]);
+        self.pos += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> Seek for OpenReader<R> {
+    fn seek(&mut self, target: SeekFrom) -> io::Result<u64> {
+        let pos = match target {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Seek before start of file")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Stretches `secret` into a 256-bit key with Argon2id.
+pub fn derive_key(secret: &[u8], salt: &[u8]) -> Result<[u8; KEY_LEN], String> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|err| err.to_string())?;
+    Ok(key)
+}
+
+/// AES-256-GCM under a raw key. Returns `nonce | ciphertext`.
+pub fn encrypt(key: &[u8; KEY_LEN], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| "Encryption failed".to_string())?;
+    let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Reverses [`encrypt`]; any tampering or a wrong key fails authentication.
+pub fn decrypt(key: &[u8; KEY_LEN], data: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < NONCE_LEN {
+        return Err("Ciphertext is truncated".to_string());
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| "Decryption failed: wrong key or damaged data".to_string())
+}
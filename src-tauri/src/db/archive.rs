@@ -0,0 +1,532 @@
+//! Encrypted, self-contained archives of a single case for handing a matter
+//! to another office: its rows from every case table plus the attachment
+//! files they reference, in a zip sealed with a passphrase.
+//!
+//! Zip layout: `manifest.json`, `rows/<table>.json` (arrays of row objects)
+//! and `attachments/<path>`, relative to the exported attachment directory.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use rusqlite::types::Value;
+use rusqlite::{Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::query::{collect_rows, Row};
+use crate::crypto::{OpenReader, SealWriter};
+use crate::paths::{resolve_app_path, resolve_save_path};
+
+const ARCHIVE_MAGIC: &[u8; 4] = b"JDCA";
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const ATTACHMENTS_PREFIX: &str = "attachments/";
+
+/// A table holding part of a case. `filter` selects its rows with the case
+/// id bound to `?1`; `references` lists the id columns to remap on import.
+struct CaseTable {
+    name: &'static str,
+    filter: &'static str,
+    references: &'static [(&'static str, &'static str)],
+    /// Column holding the absolute path of an attachment file.
+    file_column: Option<&'static str>,
+}
+
+/// In dependency order: every table comes after the ones it references.
+const CASE_TABLES: &[CaseTable] = &[
+    CaseTable {
+        name: "clients",
+        filter: "id = (SELECT client_id FROM cases WHERE id = ?1)",
+        references: &[],
+        file_column: None,
+    },
+    CaseTable {
+        name: "cases",
+        filter: "id = ?1",
+        references: &[("client_id", "clients")],
+        file_column: None,
+    },
+    CaseTable {
+        name: "document_folders",
+        filter: "case_id = ?1",
+        references: &[
+            ("case_id", "cases"),
+            ("client_id", "clients"),
+            ("parent_id", "document_folders"),
+        ],
+        file_column: None,
+    },
+    CaseTable {
+        name: "documents",
+        filter: "case_id = ?1",
+        references: &[
+            ("case_id", "cases"),
+            ("client_id", "clients"),
+            ("folder_id", "document_folders"),
+        ],
+        file_column: Some("file_path"),
+    },
+    CaseTable {
+        name: "deadlines",
+        filter: "case_id = ?1",
+        references: &[("case_id", "cases"), ("client_id", "clients")],
+        file_column: None,
+    },
+    CaseTable {
+        name: "chat_sessions",
+        filter: "case_id = ?1",
+        references: &[("case_id", "cases")],
+        file_column: None,
+    },
+    CaseTable {
+        name: "chat_messages",
+        filter: "session_id IN (SELECT id FROM chat_sessions WHERE case_id = ?1)",
+        references: &[("session_id", "chat_sessions")],
+        file_column: None,
+    },
+    CaseTable {
+        name: "chat_attachments",
+        filter: "session_id IN (SELECT id FROM chat_sessions WHERE case_id = ?1)",
+        references: &[("session_id", "chat_sessions")],
+        file_column: Some("file_path"),
+    },
+];
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format_version: u32,
+    /// `user_version` of the exporting database.
+    schema_version: i64,
+    exported_at_ms: i64,
+    case_id: i64,
+    /// Row count per exported table.
+    tables: BTreeMap<String, usize>,
+    attachments: Vec<AttachmentEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct AttachmentEntry {
+    /// Zip entry name, under `attachments/`.
+    entry: String,
+    /// Path recorded in the rows, rewritten on import.
+    original_path: String,
+    size: u64,
+}
+
+/// `ZipError`'s own message for I/O failures is just "i/o error", which
+/// would hide a failed authentication tag from [`OpenReader`].
+fn zip_error(err: ZipError) -> String {
+    match err {
+        ZipError::Io(err) => err.to_string(),
+        err => err.to_string(),
+    }
+}
+
+fn rows_entry(table: &str) -> String {
+    format!("rows/{}.json", table)
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM pragma_table_info(?1)")
+        .map_err(|err| err.to_string())?;
+    let columns = stmt
+        .query_map([table], |row| row.get(0))
+        .map_err(|err| err.to_string())?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|err| err.to_string())?;
+    Ok(columns)
+}
+
+fn user_version(conn: &Connection) -> Result<i64, String> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|err| err.to_string())
+}
+
+/// Zip entry name for `file` if it lies inside `root`, with `/` separators.
+fn attachment_entry(root: &Path, file: &Path) -> Option<String> {
+    let file = dunce::canonicalize(file).ok()?;
+    let relative = file.strip_prefix(root).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(format!("{}{}", ATTACHMENTS_PREFIX, parts.join("/")))
+}
+
+fn export(
+    db_path: &Path,
+    case_id: i64,
+    attachment_root: &Path,
+    dest: &Path,
+    passphrase: &str,
+) -> Result<(), String> {
+    let conn = super::open_readonly(db_path)?;
+    let found: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM cases WHERE id = ?1)",
+            [case_id],
+            |row| row.get(0),
+        )
+        .map_err(|err| err.to_string())?;
+    if !found {
+        return Err(format!("Case {} not found", case_id));
+    }
+    let attachment_root = dunce::canonicalize(attachment_root).map_err(|err| err.to_string())?;
+
+    let mut tables = Vec::new();
+    let mut attachments: Vec<(AttachmentEntry, PathBuf)> = Vec::new();
+    for table in CASE_TABLES {
+        if table_columns(&conn, table.name)?.is_empty() {
+            continue;
+        }
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT * FROM \"{}\" WHERE {} ORDER BY id",
+                table.name, table.filter
+            ))
+            .map_err(|err| err.to_string())?;
+        let rows = collect_rows(&mut stmt, vec![Value::Integer(case_id)])?;
+        for path in table
+            .file_column
+            .iter()
+            .flat_map(|column| rows.iter().filter_map(|row| row.get(*column)?.as_str()))
+        {
+            if attachments
+                .iter()
+                .any(|(entry, _)| entry.original_path == path)
+            {
+                continue;
+            }
+            match attachment_entry(&attachment_root, Path::new(path)) {
+                Some(entry) => {
+                    let size = fs::metadata(path).map_err(|err| err.to_string())?.len();
+                    let attachment = AttachmentEntry {
+                        entry,
+                        original_path: path.to_string(),
+                        size,
+                    };
+                    attachments.push((attachment, PathBuf::from(path)));
+                }
+                None => log::warn!(
+                    "Skipping attachment outside {} or missing: {}",
+                    attachment_root.display(),
+                    path
+                ),
+            }
+        }
+        tables.push((table.name, rows));
+    }
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        schema_version: user_version(&conn)?,
+        exported_at_ms: chrono::Utc::now().timestamp_millis(),
+        case_id,
+        tables: tables
+            .iter()
+            .map(|(name, rows)| (name.to_string(), rows.len()))
+            .collect(),
+        attachments: attachments
+            .iter()
+            .map(|(entry, _)| entry)
+            .cloned()
+            .collect(),
+    };
+    drop(conn);
+
+    let sealer = SealWriter::new(
+        BufWriter::new(File::create(dest).map_err(|err| err.to_string())?),
+        ARCHIVE_MAGIC,
+        passphrase,
+    )?;
+    let mut zip = ZipWriter::new_stream(sealer);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    for (name, rows) in &tables {
+        zip.start_file(rows_entry(name), options)
+            .map_err(|err| err.to_string())?;
+        serde_json::to_writer(&mut zip, rows).map_err(|err| err.to_string())?;
+    }
+    for (attachment, source) in &attachments {
+        zip.start_file(attachment.entry.as_str(), options)
+            .map_err(|err| err.to_string())?;
+        let mut file = File::open(source).map_err(|err| err.to_string())?;
+        io::copy(&mut file, &mut zip).map_err(|err| err.to_string())?;
+    }
+    zip.start_file(MANIFEST_ENTRY, options)
+        .map_err(|err| err.to_string())?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|err| err.to_string())?;
+
+    let sealer = zip.finish().map_err(|err| err.to_string())?.into_inner();
+    let file = sealer
+        .finish()
+        .map_err(|err| err.to_string())?
+        .into_inner()
+        .map_err(|err| err.to_string())?;
+    file.sync_all().map_err(|err| err.to_string())
+}
+
+/// Writes the case with id `case_id` from `db_path`, plus the files its
+/// documents and chat attachments reference inside `attachment_dir`, to an
+/// archive at `dest` encrypted under `passphrase`. `attachment_dir` is
+/// relative to the app data dir, as is `dest` unless it comes from
+/// `prompt_save_path`. Files are streamed, never held in memory whole.
+#[tauri::command]
+pub async fn export_case_archive(
+    app: AppHandle,
+    db_path: String,
+    case_id: i64,
+    attachment_dir: String,
+    dest: String,
+    passphrase: String,
+) -> Result<(), String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    let attachment_root = resolve_app_path(&app, &attachment_dir)?;
+    let dest = resolve_save_path(&app, &dest)?;
+    super::run_blocking(move || {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let tmp = super::sidecar_path(&dest, ".partial");
+        let result = export(&db_path, case_id, &attachment_root, &tmp, &passphrase)
+            .and_then(|()| fs::rename(&tmp, &dest).map_err(|err| err.to_string()));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    })
+    .await
+}
+
+/// JSON from an archive back to SQLite. Blobs were exported as byte arrays.
+fn json_to_sql(value: &serde_json::Value) -> Result<Value, String> {
+    Ok(match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(flag) => Value::Integer(i64::from(*flag)),
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(integer) => Value::Integer(integer),
+            None => Value::Real(number.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(text) => Value::Text(text.clone()),
+        serde_json::Value::Array(items) => Value::Blob(
+            items
+                .iter()
+                .map(|item| {
+                    item.as_u64()
+                        .and_then(|byte| u8::try_from(byte).ok())
+                        .ok_or_else(|| "Archive contains an invalid blob".to_string())
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        serde_json::Value::Object(_) => {
+            return Err("Archive contains an unexpected object value".to_string())
+        }
+    })
+}
+
+/// First of `path`, `path_1`, `path_2`, ... that doesn't exist yet.
+fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|counter| path.with_file_name(format!("{}_{}{}", stem, counter, extension)))
+        .find(|candidate| !candidate.exists())
+        .expect("an unused file name exists")
+}
+
+/// Extracts the attachments into `root` and returns original path -> new path.
+fn extract_attachments<R: io::Read + io::Seek>(
+    zip: &mut ZipArchive<R>,
+    manifest: &Manifest,
+    root: &Path,
+    written: &mut Vec<PathBuf>,
+) -> Result<HashMap<String, String>, String> {
+    let mut moved = HashMap::new();
+    for attachment in &manifest.attachments {
+        let mut entry = zip
+            .by_name(&attachment.entry)
+            .map_err(|err| format!("{}: {}", attachment.entry, zip_error(err)))?;
+        // `enclosed_name` rejects `..` and absolute names.
+        let relative = entry
+            .enclosed_name()
+            .and_then(|name| {
+                name.strip_prefix(ATTACHMENTS_PREFIX.trim_end_matches('/'))
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .filter(|relative| relative.file_name().is_some())
+            .ok_or_else(|| format!("Invalid attachment name: {}", attachment.entry))?;
+        let target = unique_path(root.join(relative));
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let mut file = File::create(&target).map_err(|err| err.to_string())?;
+        written.push(target.clone());
+        io::copy(&mut entry, &mut file).map_err(|err| err.to_string())?;
+        file.sync_all().map_err(|err| err.to_string())?;
+        moved.insert(
+            attachment.original_path.clone(),
+            target.display().to_string(),
+        );
+    }
+    Ok(moved)
+}
+
+/// Inserts the archived rows with fresh ids, rewriting references and
+/// attachment paths. Returns the new case id.
+fn insert_rows<R: io::Read + io::Seek>(
+    conn: &mut Connection,
+    zip: &mut ZipArchive<R>,
+    manifest: &Manifest,
+    moved: &HashMap<String, String>,
+) -> Result<i64, String> {
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|err| err.to_string())?;
+    let mut new_ids: HashMap<&str, HashMap<i64, i64>> = HashMap::new();
+    for table in CASE_TABLES {
+        if !manifest.tables.contains_key(table.name) {
+            continue;
+        }
+        let columns = table_columns(&tx, table.name)?;
+        if columns.is_empty() {
+            log::warn!("Skipping archived table missing here: {}", table.name);
+            continue;
+        }
+        let entry = zip
+            .by_name(&rows_entry(table.name))
+            .map_err(|err| format!("{}: {}", table.name, zip_error(err)))?;
+        let rows: Vec<Row> =
+            serde_json::from_reader(BufReader::new(entry)).map_err(|err| err.to_string())?;
+
+        for mut row in rows {
+            let old_id = row.remove("id").and_then(|id| id.as_i64());
+            for (column, target) in table.references {
+                if let Some(value) = row.get_mut(*column) {
+                    // Ids outside the archive (e.g. a folder's parent that
+                    // belongs to no case) can't be resolved and are cleared.
+                    let mapped = value
+                        .as_i64()
+                        .and_then(|old| new_ids.get(target)?.get(&old).copied());
+                    *value = mapped.map_or(serde_json::Value::Null, Into::into);
+                }
+            }
+            if let Some(column) = table.file_column {
+                if let Some(value) = row.get_mut(column) {
+                    if let Some(path) = value.as_str().and_then(|path| moved.get(path)) {
+                        *value = path.clone().into();
+                    }
+                }
+            }
+
+            let (names, values): (Vec<&String>, Vec<Value>) = row
+                .iter()
+                .filter(|(name, _)| columns.contains(name))
+                .map(|(name, value)| Ok((name, json_to_sql(value)?)))
+                .collect::<Result<Vec<_>, String>>()?
+                .into_iter()
+                .unzip();
+            let sql = format!(
+                "INSERT INTO \"{}\" ({}) VALUES ({})",
+                table.name,
+                names
+                    .iter()
+                    .map(|name| format!("\"{}\"", name.replace('"', "\"\"")))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                vec!["?"; names.len()].join(", ")
+            );
+            tx.execute(&sql, rusqlite::params_from_iter(values))
+                .map_err(|err| format!("Failed to import into {}: {}", table.name, err))?;
+            if let Some(old_id) = old_id {
+                new_ids
+                    .entry(table.name)
+                    .or_default()
+                    .insert(old_id, tx.last_insert_rowid());
+            }
+        }
+    }
+    let case_id = new_ids
+        .get("cases")
+        .and_then(|ids| ids.get(&manifest.case_id))
+        .copied()
+        .ok_or_else(|| "Archive does not contain its case".to_string())?;
+    tx.commit().map_err(|err| err.to_string())?;
+    Ok(case_id)
+}
+
+fn import(
+    db_path: &Path,
+    archive: &Path,
+    attachment_root: &Path,
+    passphrase: &str,
+) -> Result<i64, String> {
+    let file = BufReader::new(File::open(archive).map_err(|err| err.to_string())?);
+    let reader = OpenReader::new(file, ARCHIVE_MAGIC, passphrase).map_err(|err| err.to_string())?;
+    let mut zip = ZipArchive::new(reader).map_err(zip_error)?;
+    let manifest: Manifest = {
+        let entry = zip
+            .by_name(MANIFEST_ENTRY)
+            .map_err(|err| format!("{}: {}", MANIFEST_ENTRY, zip_error(err)))?;
+        serde_json::from_reader(BufReader::new(entry)).map_err(|err| err.to_string())?
+    };
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "Archive format {} is newer than this version of JurisDesk supports",
+            manifest.format_version
+        ));
+    }
+
+    let mut conn = super::open(db_path)?;
+    let schema_version = user_version(&conn)?;
+    if manifest.schema_version > schema_version {
+        return Err(format!(
+            "Archive was made with a newer database schema ({} > {}); update JurisDesk first",
+            manifest.schema_version, schema_version
+        ));
+    }
+
+    // Files go first so the rows can point at them; they are removed again
+    // if anything fails before the rows are committed.
+    let mut written = Vec::new();
+    let result = extract_attachments(&mut zip, &manifest, attachment_root, &mut written)
+        .and_then(|moved| insert_rows(&mut conn, &mut zip, &manifest, &moved));
+    if result.is_err() {
+        for path in &written {
+            let _ = fs::remove_file(path);
+        }
+    }
+    result
+}
+
+/// Imports an archive made by `export_case_archive` into `db_path` as a new
+/// case (with its client and related rows), extracting attachments under
+/// `attachment_dir`. Returns the new case id.
+#[tauri::command]
+pub async fn import_case_archive(
+    app: AppHandle,
+    db_path: String,
+    archive: String,
+    attachment_dir: String,
+    passphrase: String,
+) -> Result<i64, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    let archive = resolve_app_path(&app, &archive)?;
+    let attachment_root = resolve_app_path(&app, &attachment_dir)?;
+    super::run_blocking(move || import(&db_path, &archive, &attachment_root, &passphrase)).await
+}
@@ -0,0 +1,249 @@
+//! Periodic encrypted backups of the primary database.
+//!
+//! The schedule lives in `settings.json` and the backup passphrase, sealed
+//! with the device key, in the secret store, so the loop started from `setup` survives restarts and picks
+//! up changes made through `set_backup_schedule` on its next check.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::notifications;
+use crate::paths::{resolve_app_path, resolve_save_path};
+use crate::progress::ProgressReporter;
+use crate::secrets::{self, device::DeviceKey};
+use crate::settings::{self, BackupSchedule};
+
+/// How often the loop checks whether a backup is due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const PASSPHRASE_SECRET: &str = "auto-backup-passphrase";
+const FILE_PREFIX: &str = "jurisdesk-backup-";
+const FILE_SUFFIX: &str = ".jdbak";
+
+fn is_due(schedule: &BackupSchedule, now_ms: i64) -> bool {
+    let interval_ms = i64::from(schedule.interval_hours) * 60 * 60 * 1000;
+    schedule
+        .last_run_ms
+        .map_or(true, |last| now_ms - last >= interval_ms)
+}
+
+/// Where backups go. `dest_dir` is normally an absolute folder the user
+/// picked (approved by `set_backup_schedule`), which must still exist: a
+/// disconnected drive or unmounted share shouldn't turn into a new, empty
+/// folder on the system disk. Schedules saved with a relative path keep it
+/// under the app data dir.
+fn backup_dir(app: &AppHandle, dest_dir: &str) -> Result<PathBuf, String> {
+    let dir = Path::new(dest_dir.trim());
+    if !dir.is_absolute() {
+        let dir = resolve_app_path(app, dest_dir)?;
+        fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+        return Ok(dir);
+    }
+    if !dir.is_dir() {
+        return Err(format!("Backup folder is not available: {}", dir.display()));
+    }
+    Ok(dir.to_path_buf())
+}
+
+/// Fails unless a file can be created in `dir`.
+fn ensure_writable(dir: &Path) -> Result<(), String> {
+    let probe = dir.join(".jurisdesk-write-test");
+    fs::write(&probe, b"").map_err(|err| format!("Cannot write to {}: {}", dir.display(), err))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Whether another connection holds the write lock right now, found out
+/// without waiting: the backup's checkpoint would otherwise sit out the busy
+/// timeout and compete with the frontend's writer in the middle of an edit.
+fn db_is_busy(db_path: &Path) -> Result<bool, String> {
+    let conn = super::open(db_path)?;
+    conn.busy_timeout(Duration::ZERO)
+        .map_err(|err| err.to_string())?;
+    match conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;") {
+        Ok(()) => Ok(false),
+        Err(err) if super::is_busy(&err) => Ok(true),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Deletes the oldest automatic backups in `dir` beyond `keep`. File names
+/// embed the timestamp, so name order is age order.
+fn prune(dir: &Path, keep: u32) -> Result<(), String> {
+    let mut backups: Vec<_> = fs::read_dir(dir)
+        .map_err(|err| err.to_string())?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX))
+        })
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep as usize);
+    for path in &backups[..excess] {
+        if let Err(err) = fs::remove_file(path) {
+            log::warn!("Failed to prune {}: {}", path.display(), err);
+        }
+    }
+    Ok(())
+}
+
+/// Runs one backup. `Ok(false)` means it was skipped and should be retried
+/// at the next check.
+async fn run_once(app: &AppHandle, schedule: &BackupSchedule) -> Result<bool, String> {
+    let db_path = super::primary_db_path(app)?;
+    if !db_path.is_file() {
+        return Ok(false);
+    }
+
+    let stored = secrets::read_internal(PASSPHRASE_SECRET)?
+        .ok_or_else(|| "No backup passphrase is configured".to_string())?;
+    let passphrase = app.state::<DeviceKey>().open(PASSPHRASE_SECRET, &stored)?;
+    let dest_dir = backup_dir(app, &schedule.dest_dir)?;
+    let file_name = format!(
+        "{}{}{}",
+        FILE_PREFIX,
+        chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+        FILE_SUFFIX
+    );
+    let keep = schedule.keep;
+    let progress = ProgressReporter::new(app, "auto_backup");
+    // A busy database is left for the next check rather than reported, so
+    // the run waits for a quiet moment instead of interrupting an edit.
+    let result = super::run_blocking(move || {
+        if db_is_busy(&db_path)? {
+            return Err(super::backup::DATABASE_BUSY.to_string());
+        }
+        super::backup::backup(&db_path, &dest_dir.join(file_name), &passphrase, &progress)?;
+        prune(&dest_dir, keep)
+    })
+    .await;
+    match result {
+        Ok(()) => Ok(true),
+        Err(err) if err == super::backup::DATABASE_BUSY => {
+            log::info!("Database busy; postponing automatic backup");
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+async fn tick(app: &AppHandle) {
+    let Some(schedule) = settings::load(app).backup_schedule else {
+        return;
+    };
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    if !is_due(&schedule, now_ms) {
+        return;
+    }
+
+    let (title, body) = match run_once(app, &schedule).await {
+        Ok(false) => return,
+        Ok(true) => (
+            "Backup concluído",
+            "O backup automático foi salvo.".to_string(),
+        ),
+        Err(err) => {
+            log::warn!("Automatic backup failed: {}", err);
+            (
+                "Falha no backup",
+                format!("O backup automático falhou: {}", err),
+            )
+        }
+    };
+    // A failed run also waits a full interval, so a persistent problem
+    // doesn't raise a notification every minute.
+    let recorded = settings::update(app, |settings| {
+        if let Some(current) = settings.backup_schedule.as_mut() {
+            current.last_run_ms = Some(now_ms);
+        }
+    });
+    if let Err(err) = recorded {
+        log::warn!("Failed to record automatic backup time: {}", err);
+    }
+    if let Err(err) = notifications::show(app, title, &body) {
+        log::warn!("Failed to show backup notification: {}", err);
+    }
+}
+
+/// Starts the background loop; call once from `setup`.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            tick(&app).await;
+        }
+    });
+}
+
+/// Configures automatic backups every `interval_hours` into `dest_dir`, keeping
+/// the newest `keep` files. `dest_dir` is an existing, writable folder: an
+/// absolute path from `prompt_folder_path` (another drive or a synced folder)
+/// or the folder of the current schedule, or a path relative to the app data
+/// dir.
+/// `interval_hours = 0` turns them off. `passphrase` is required the first
+/// time and kept in the secret store; omit it to keep the current one.
+#[tauri::command]
+pub fn set_backup_schedule(
+    app: AppHandle,
+    interval_hours: u32,
+    dest_dir: String,
+    keep: u32,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    if interval_hours == 0 {
+        return settings::update(&app, |settings| settings.backup_schedule = None);
+    }
+    if keep == 0 {
+        return Err("keep must be at least 1".to_string());
+    }
+    // `prune` deletes files in this folder, so it must be one the user chose.
+    let dest_dir = dest_dir.trim().to_string();
+    let unchanged = settings::load(&app)
+        .backup_schedule
+        .is_some_and(|current| current.dest_dir.trim() == dest_dir);
+    if Path::new(&dest_dir).is_absolute() && !unchanged {
+        resolve_save_path(&app, &dest_dir)?;
+    }
+    ensure_writable(&backup_dir(&app, &dest_dir)?)?;
+    match passphrase {
+        Some(passphrase) if passphrase.is_empty() => {
+            return Err("Passphrase must not be empty".to_string())
+        }
+        Some(passphrase) => {
+            let sealed = app
+                .state::<DeviceKey>()
+                .seal(PASSPHRASE_SECRET, &passphrase)?;
+            secrets::write_internal(PASSPHRASE_SECRET, &sealed)?
+        }
+        None => {
+            if secrets::read_internal(PASSPHRASE_SECRET)?.is_none() {
+                return Err("A passphrase is required to enable automatic backups".to_string());
+            }
+        }
+    }
+
+    settings::update(&app, |settings| {
+        let last_run_ms = settings
+            .backup_schedule
+            .as_ref()
+            .and_then(|current| current.last_run_ms);
+        settings.backup_schedule = Some(BackupSchedule {
+            interval_hours,
+            dest_dir,
+            keep,
+            last_run_ms,
+        });
+    })
+}
+
+/// Current schedule, or `None` when automatic backups are off.
+#[tauri::command]
+pub fn get_backup_schedule(app: AppHandle) -> Result<Option<BackupSchedule>, String> {
+    Ok(settings::load(&app).backup_schedule)
+}
@@ -0,0 +1,430 @@
+//! Encrypted, portable copies of the primary database.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::crypto;
+use crate::paths::{resolve_app_path, resolve_save_path};
+use crate::progress::ProgressReporter;
+
+const BACKUP_MAGIC: &[u8; 4] = b"JDDB";
+/// Starts the decrypted payload: `PAYLOAD_MAGIC | created_at_ms (i64 LE) |
+/// database`. Older backups hold the bare database.
+const PAYLOAD_MAGIC: &[u8; 4] = b"JDBM";
+const PAYLOAD_HEADER_LEN: usize = PAYLOAD_MAGIC.len() + 8;
+/// Returned by [`backup`] when writers kept the database busy throughout.
+pub(super) const DATABASE_BUSY: &str = "Database is busy; try again once pending writes finish";
+const SNAPSHOT_ATTEMPTS: usize = 3;
+
+/// Reads a consistent copy of the database file with the WAL folded in.
+///
+/// After a TRUNCATE checkpoint the main file holds every committed page. A
+/// read transaction opened while the WAL is still empty keeps other
+/// connections from checkpointing newer frames into the file during the copy;
+/// if a writer slipped in first we checkpoint again.
+fn snapshot(db_path: &Path) -> Result<Vec<u8>, String> {
+    let mut conn = super::open(db_path)?;
+    let wal_path = super::sidecar_path(db_path, "-wal");
+
+    for _ in 0..SNAPSHOT_ATTEMPTS {
+        let (busy, _, _) = super::checkpoint_truncate(&conn)?;
+        if busy != 0 {
+            continue;
+        }
+
+        let tx = conn.transaction().map_err(|err| err.to_string())?;
+        tx.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+            .map_err(|err| err.to_string())?;
+        let wal_len = fs::metadata(&wal_path).map(|meta| meta.len()).unwrap_or(0);
+        if wal_len == 0 {
+            return fs::read(db_path).map_err(|err| err.to_string());
+        }
+    }
+
+    Err(DATABASE_BUSY.to_string())
+}
+
+fn encode_payload(created_at_ms: i64, database: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(PAYLOAD_HEADER_LEN + database.len());
+    payload.extend_from_slice(PAYLOAD_MAGIC);
+    payload.extend_from_slice(&created_at_ms.to_le_bytes());
+    payload.extend_from_slice(database);
+    payload
+}
+
+/// Splits a decrypted backup into its creation time (`None` for the older
+/// format) and the database file.
+fn decode_payload(plaintext: &[u8]) -> Result<(Option<i64>, &[u8]), String> {
+    let (created_at_ms, database) = match plaintext.strip_prefix(PAYLOAD_MAGIC) {
+        Some(rest) if plaintext.len() >= PAYLOAD_HEADER_LEN => {
+            let (timestamp, database) = rest.split_at(PAYLOAD_HEADER_LEN - PAYLOAD_MAGIC.len());
+            let timestamp = <[u8; 8]>::try_from(timestamp).map_err(|err| err.to_string())?;
+            (Some(i64::from_le_bytes(timestamp)), database)
+        }
+        _ => (None, plaintext),
+    };
+    if !database.starts_with(super::SQLITE_HEADER) {
+        return Err("Backup does not contain a SQLite database".to_string());
+    }
+    Ok((created_at_ms, database))
+}
+
+const BACKUP_STEPS: u64 = 3;
+const RESTORE_STEPS: u64 = 3;
+
+pub(super) fn backup(
+    db_path: &Path,
+    dest_path: &Path,
+    passphrase: &str,
+    progress: &ProgressReporter,
+) -> Result<(), String> {
+    progress.report(0, BACKUP_STEPS, "Reading database");
+    let plaintext = encode_payload(chrono::Utc::now().timestamp_millis(), &snapshot(db_path)?);
+    progress.report(1, BACKUP_STEPS, "Encrypting");
+    let sealed = crypto::seal(BACKUP_MAGIC, passphrase, &plaintext)?;
+    progress.report(2, BACKUP_STEPS, "Writing backup");
+    fs::write(dest_path, sealed).map_err(|err| err.to_string())?;
+    progress.report(BACKUP_STEPS, BACKUP_STEPS, "Backup complete");
+    Ok(())
+}
+
+fn restore(
+    db_path: &Path,
+    src_path: &Path,
+    passphrase: &str,
+    progress: &ProgressReporter,
+) -> Result<(), String> {
+    progress.report(0, RESTORE_STEPS, "Reading backup");
+    let data = fs::read(src_path).map_err(|err| err.to_string())?;
+    progress.report(1, RESTORE_STEPS, "Decrypting");
+    let plaintext = crypto::open(BACKUP_MAGIC, passphrase, &data).map_err(|err| err.to_string())?;
+    let (_, database) = decode_payload(&plaintext)?;
+    progress.report(2, RESTORE_STEPS, "Replacing database");
+
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let staging = super::sidecar_path(db_path, ".restoring");
+    fs::write(&staging, database).map_err(|err| err.to_string())?;
+
+    // Fold the live WAL into its file first, so a failed rename leaves the
+    // current database complete, and a leftover WAL can't be replayed onto
+    // the restored one.
+    if db_path.is_file() {
+        let checkpointed = super::open(db_path)
+            .and_then(|conn| super::checkpoint_truncate(&conn))
+            .and_then(|(busy, _, _)| match busy {
+                0 => Ok(()),
+                _ => Err(DATABASE_BUSY.to_string()),
+            });
+        if let Err(err) = checkpointed {
+            let _ = fs::remove_file(&staging);
+            return Err(err);
+        }
+    }
+    if let Err(err) = fs::rename(&staging, db_path) {
+        let _ = fs::remove_file(&staging);
+        return Err(err.to_string());
+    }
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let sidecar = super::sidecar_path(db_path, suffix);
+        if let Err(err) = fs::remove_file(&sidecar) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove {}: {}", sidecar.display(), err);
+            }
+        }
+    }
+    progress.report(RESTORE_STEPS, RESTORE_STEPS, "Restore complete");
+    Ok(())
+}
+
+/// Writes an encrypted copy of the primary database to `dest_path` (relative
+/// to the app data dir, or a path from `prompt_save_path`). Progress is
+/// reported under `task_id` (defaults to `"backup_database"`).
+#[tauri::command]
+pub async fn backup_database(
+    app: AppHandle,
+    dest_path: String,
+    passphrase: String,
+    task_id: Option<String>,
+) -> Result<(), String> {
+    let db_path = super::primary_db_path(&app)?;
+    let dest_path = resolve_save_path(&app, &dest_path)?;
+    let progress = ProgressReporter::new(&app, task_id.unwrap_or_else(|| "backup_database".into()));
+    super::run_blocking(move || backup(&db_path, &dest_path, &passphrase, &progress)).await
+}
+
+/// Replaces the primary database with the backup at `src_path` (relative to
+/// the app data dir). The frontend must close its connection first; the
+/// backup is fully decrypted and checked before the current file is touched.
+/// Progress is reported under `task_id` (defaults to `"restore_database"`).
+#[tauri::command]
+pub async fn restore_database(
+    app: AppHandle,
+    src_path: String,
+    passphrase: String,
+    task_id: Option<String>,
+) -> Result<(), String> {
+    if super::primary_db_is_open(&app).await {
+        return Err("The database is open; close it before restoring".to_string());
+    }
+    let db_path = super::primary_db_path(&app)?;
+    let src_path = resolve_app_path(&app, &src_path)?;
+    let progress =
+        ProgressReporter::new(&app, task_id.unwrap_or_else(|| "restore_database".into()));
+    super::run_blocking(move || restore(&db_path, &src_path, &passphrase, &progress)).await
+}
+
+/// Tables whose row counts `verify_backup` reports, when the backup has them.
+const KEY_TABLES: &[&str] = &["clients", "cases", "documents", "deadlines"];
+/// Integrity messages quoted in the error for a damaged backup.
+const MAX_REPORTED_PROBLEMS: usize = 5;
+
+#[derive(Serialize)]
+pub struct BackupInfo {
+    /// `user_version` of the backed-up database.
+    pub schema_version: i64,
+    pub row_counts: BTreeMap<String, i64>,
+    /// When the backup was taken; `None` for backups made before the format
+    /// recorded it.
+    pub created_at_ms: Option<i64>,
+}
+
+/// A decrypted copy of a backup that only the current user can read. Dropping
+/// it deletes the file along with any journal SQLite left next to it.
+struct TempDatabase(PathBuf);
+
+impl TempDatabase {
+    fn create(dir: &Path, contents: &[u8]) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        let suffix: String = crypto::random_bytes::<8>()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let path = dir.join(format!(".jurisdesk-verify-{}.db", suffix));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&path).map_err(|err| err.to_string())?;
+        let temp = TempDatabase(path);
+        file.write_all(contents).map_err(|err| err.to_string())?;
+        Ok(temp)
+    }
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        let sidecars =
+            ["-wal", "-shm", "-journal"].map(|suffix| super::sidecar_path(&self.0, suffix));
+        for path in std::iter::once(self.0.clone()).chain(sidecars) {
+            if let Err(err) = fs::remove_file(&path) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to remove {}: {}", path.display(), err);
+                }
+            }
+        }
+    }
+}
+
+fn inspect(db_path: &Path) -> Result<(i64, BTreeMap<String, i64>), String> {
+    let conn = super::open_readonly(db_path)?;
+    let problems = conn
+        .prepare("PRAGMA integrity_check")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .map_err(|err| format!("Backup database is damaged: {}", err))?;
+    if problems.len() != 1 || problems[0] != "ok" {
+        return Err(format!(
+            "Backup database is damaged: {}",
+            problems[..problems.len().min(MAX_REPORTED_PROBLEMS)].join("; ")
+        ));
+    }
+
+    let schema_version = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|err| err.to_string())?;
+    let mut row_counts = BTreeMap::new();
+    for table in KEY_TABLES {
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )
+            .map_err(|err| err.to_string())?;
+        if exists {
+            let count = conn
+                .query_row(&format!("SELECT count(*) FROM \"{}\"", table), [], |row| {
+                    row.get(0)
+                })
+                .map_err(|err| err.to_string())?;
+            row_counts.insert(table.to_string(), count);
+        }
+    }
+    Ok((schema_version, row_counts))
+}
+
+fn verify(src_path: &Path, temp_dir: &Path, passphrase: &str) -> Result<BackupInfo, String> {
+    let data = fs::read(src_path).map_err(|err| err.to_string())?;
+    // "Wrong passphrase" and "File is corrupt: ..." come from here.
+    let plaintext = crypto::open(BACKUP_MAGIC, passphrase, &data).map_err(|err| err.to_string())?;
+    let (created_at_ms, database) = decode_payload(&plaintext)?;
+
+    let temp = TempDatabase::create(temp_dir, database)?;
+    let (schema_version, row_counts) = inspect(&temp.0)?;
+    Ok(BackupInfo {
+        schema_version,
+        row_counts,
+        created_at_ms,
+    })
+}
+
+/// Checks a `backup_database` file at `src_path` (relative to the app data
+/// dir) without touching the live database: decrypts it to a private
+/// temporary file in the app data dir, runs `PRAGMA integrity_check` there
+/// and reports what it contains. The temporary copy and its journals are
+/// deleted afterwards, whatever the outcome. A wrong
+/// passphrase, a damaged file and a damaged database inside it each fail with
+/// their own message.
+#[tauri::command]
+pub async fn verify_backup(
+    app: AppHandle,
+    src_path: String,
+    passphrase: String,
+) -> Result<BackupInfo, String> {
+    let src_path = resolve_app_path(&app, &src_path)?;
+    let temp_dir = crate::paths::data_dir(&app)?;
+    super::run_blocking(move || verify(&src_path, &temp_dir, &passphrase)).await
+}
+
+/// Pause between backup steps, during which other connections can read and
+/// write the source.
+const STEP_PAUSE: Duration = Duration::from_millis(10);
+/// Pause before retrying a step that couldn't get its lock.
+const BUSY_PAUSE: Duration = Duration::from_millis(50);
+/// Consecutive busy steps tolerated before the backup gives up (~10s).
+const MAX_BUSY_STEPS: u32 = 200;
+
+#[derive(Serialize)]
+pub struct BackupResult {
+    pub pages: i64,
+    pub steps: u32,
+    pub elapsed_ms: u64,
+}
+
+fn online_backup(
+    src_path: &Path,
+    dest_path: &Path,
+    pages_per_step: u32,
+    progress: &ProgressReporter,
+) -> Result<BackupResult, String> {
+    let started = Instant::now();
+    let source = super::open_readonly(src_path)?;
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    // Written under a temporary name and renamed at the end, so `dest_path`
+    // is never a half-copied database.
+    let staging = super::sidecar_path(dest_path, ".partial");
+    let _ = fs::remove_file(&staging);
+
+    let result = (|| {
+        let mut dest = Connection::open_with_flags(
+            &staging,
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|err| err.to_string())?;
+        let backup = Backup::new(&source, &mut dest).map_err(|err| err.to_string())?;
+        let step_pages = i32::try_from(pages_per_step).unwrap_or(i32::MAX);
+        let mut steps = 0;
+        let mut busy_steps = 0;
+        loop {
+            let outcome = backup.step(step_pages).map_err(|err| err.to_string())?;
+            steps += 1;
+            let state = backup.progress();
+            let total = u64::try_from(state.pagecount).unwrap_or(0);
+            let remaining = u64::try_from(state.remaining).unwrap_or(0);
+            progress.report(
+                total - remaining.min(total),
+                total,
+                &format!("{} of {} pages remaining", remaining, total),
+            );
+            match outcome {
+                StepResult::Done => {
+                    return Ok((i64::from(state.pagecount), steps));
+                }
+                StepResult::More => {
+                    busy_steps = 0;
+                    std::thread::sleep(STEP_PAUSE);
+                }
+                StepResult::Busy | StepResult::Locked => {
+                    busy_steps += 1;
+                    if busy_steps > MAX_BUSY_STEPS {
+                        return Err("Database stayed busy; backup abandoned".to_string());
+                    }
+                    std::thread::sleep(BUSY_PAUSE);
+                }
+                // `StepResult` is non-exhaustive.
+                _ => return Err("Unexpected backup step result".to_string()),
+            }
+        }
+    })();
+
+    match result {
+        Ok((pages, steps)) => {
+            fs::rename(&staging, dest_path).map_err(|err| err.to_string())?;
+            Ok(BackupResult {
+                pages,
+                steps,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            })
+        }
+        Err(err) => {
+            let _ = fs::remove_file(&staging);
+            Err(err)
+        }
+    }
+}
+
+/// Copies `src_db` (see `resolve_db_path`) to `dest_db` (relative to the app
+/// data dir) with SQLite's online backup API, `pages_per_step` pages at a
+/// time with short pauses in between, so the source stays usable throughout.
+/// Unlike `backup_database` the copy is a plain, unencrypted database.
+/// Progress is reported under `task_id` (defaults to `"incremental_backup"`).
+#[tauri::command]
+pub async fn incremental_backup(
+    app: AppHandle,
+    src_db: String,
+    dest_db: String,
+    pages_per_step: u32,
+    task_id: Option<String>,
+) -> Result<BackupResult, String> {
+    if pages_per_step == 0 {
+        return Err("pages_per_step must be at least 1".to_string());
+    }
+    let src_path = super::resolve_db_path(&app, &src_db)?;
+    let dest_path = resolve_app_path(&app, &dest_db)?;
+    if src_path == dest_path {
+        return Err("Source and destination must differ".to_string());
+    }
+    let progress =
+        ProgressReporter::new(&app, task_id.unwrap_or_else(|| "incremental_backup".into()));
+    super::run_blocking(move || online_backup(&src_path, &dest_path, pages_per_step, &progress))
+        .await
+}
@@ -0,0 +1,251 @@
+//! Spreadsheet imports (e.g. a client list exported from Excel) into an
+//! existing table.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek};
+use std::path::Path;
+
+use rusqlite::types::Value;
+use rusqlite::{Connection, TransactionBehavior};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use super::validate_identifier;
+use crate::paths::resolve_app_path;
+
+/// Row errors beyond this many are counted but not listed.
+const MAX_REPORTED_ERRORS: usize = 100;
+
+#[derive(Serialize)]
+pub struct RowError {
+    /// 1-based line in the CSV file where the record starts.
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub inserted: u64,
+    pub skipped: u64,
+    /// The first 100 row errors; `skipped` counts all of them.
+    pub errors: Vec<RowError>,
+}
+
+/// How a CSV field is converted, from the column's declared type following
+/// SQLite's affinity rules.
+#[derive(Clone, Copy)]
+enum Affinity {
+    Integer,
+    Real,
+    Numeric,
+    Text,
+}
+
+impl Affinity {
+    fn from_declared(declared: &str) -> Self {
+        let declared = declared.to_ascii_uppercase();
+        if declared.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT", "BLOB"]
+            .iter()
+            .any(|name| declared.contains(name))
+            || declared.is_empty()
+        {
+            Affinity::Text
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|name| declared.contains(name))
+        {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    /// Empty fields become NULL. Numbers may use a decimal comma, as
+    /// spreadsheets in Brazilian locales export them.
+    fn coerce(self, field: &str) -> Result<Value, String> {
+        let trimmed = field.trim();
+        if trimmed.is_empty() {
+            return Ok(Value::Null);
+        }
+        let decimal = || -> Option<f64> {
+            trimmed.parse().ok().or_else(|| {
+                if trimmed.contains('.') {
+                    return None;
+                }
+                trimmed.replacen(',', ".", 1).parse().ok()
+            })
+        };
+        match self {
+            Affinity::Text => Ok(Value::Text(field.to_string())),
+            Affinity::Integer => trimmed
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| format!("'{}' is not an integer", trimmed)),
+            Affinity::Real => decimal()
+                .map(Value::Real)
+                .ok_or_else(|| format!("'{}' is not a number", trimmed)),
+            Affinity::Numeric => match trimmed.parse() {
+                Ok(integer) => Ok(Value::Integer(integer)),
+                Err(_) => decimal()
+                    .map(Value::Real)
+                    .ok_or_else(|| format!("'{}' is not a number", trimmed)),
+            },
+        }
+    }
+}
+
+/// Declared type affinity of every column of `table`; empty if it doesn't exist.
+fn column_affinities(conn: &Connection, table: &str) -> Result<HashMap<String, Affinity>, String> {
+    let mut stmt = conn
+        .prepare("SELECT name, type FROM pragma_table_info(?1)")
+        .map_err(|err| err.to_string())?;
+    let columns = stmt
+        .query_map([table], |row| {
+            let declared: String = row.get(1)?;
+            Ok((row.get(0)?, Affinity::from_declared(&declared)))
+        })
+        .map_err(|err| err.to_string())?
+        .collect::<rusqlite::Result<_>>()
+        .map_err(|err| err.to_string())?;
+    Ok(columns)
+}
+
+/// `;` when the header line has more of them than commas, which is what
+/// Excel writes in locales that use a decimal comma.
+fn sniff_delimiter(reader: &mut BufReader<File>) -> Result<u8, String> {
+    let mut header = String::new();
+    reader
+        .read_line(&mut header)
+        .map_err(|err| err.to_string())?;
+    reader.rewind().map_err(|err| err.to_string())?;
+    let count = |delimiter| header.matches(delimiter).count();
+    Ok(if count(';') > count(',') { b';' } else { b',' })
+}
+
+fn import(
+    db_path: &Path,
+    table: &str,
+    csv_path: &Path,
+    column_map: &[(String, String)],
+    strict: bool,
+) -> Result<ImportReport, String> {
+    validate_identifier(table)?;
+    if column_map.is_empty() {
+        return Err("column_map must map at least one column".to_string());
+    }
+    let mut conn = super::open(db_path)?;
+    let affinities = column_affinities(&conn, table)?;
+    if affinities.is_empty() {
+        return Err(format!("Table '{}' does not exist", table));
+    }
+
+    let mut file = BufReader::new(File::open(csv_path).map_err(|err| err.to_string())?);
+    let delimiter = sniff_delimiter(&mut file)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(file);
+    let headers = reader.headers().map_err(|err| err.to_string())?.clone();
+
+    // (CSV field index, column affinity) per mapped column, in insert order.
+    let mut fields = Vec::with_capacity(column_map.len());
+    let mut columns: Vec<&str> = Vec::with_capacity(column_map.len());
+    for (header, column) in column_map {
+        let affinity = *affinities
+            .get(column)
+            .ok_or_else(|| format!("Table '{}' has no column '{}'", table, column))?;
+        if columns.contains(&column.as_str()) {
+            return Err(format!("Column '{}' is mapped more than once", column));
+        }
+        let index = headers
+            .iter()
+            .position(|name| name.trim() == header.trim())
+            .ok_or_else(|| format!("CSV has no column '{}'", header))?;
+        fields.push((index, affinity));
+        columns.push(column);
+    }
+    // Column names were checked against the table above, so quoting them is
+    // enough.
+    let sql = format!(
+        "INSERT INTO \"{}\" ({}) VALUES ({})",
+        table,
+        columns
+            .iter()
+            .map(|column| format!("\"{}\"", column.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|err| err.to_string())?;
+    let mut report = ImportReport {
+        inserted: 0,
+        skipped: 0,
+        errors: Vec::new(),
+    };
+    {
+        let mut stmt = tx.prepare(&sql).map_err(|err| err.to_string())?;
+        for record in reader.records() {
+            // A failed INSERT only undoes its own statement, so the rows
+            // before it stay in the transaction.
+            let (line, result) = match record {
+                Ok(record) => {
+                    let line = record.position().map_or(0, |pos| pos.line());
+                    let field = |index: usize| record.get(index).unwrap_or("");
+                    let result = fields
+                        .iter()
+                        .map(|(index, affinity)| affinity.coerce(field(*index)))
+                        .collect::<Result<Vec<_>, _>>()
+                        .and_then(|values| {
+                            stmt.execute(rusqlite::params_from_iter(values))
+                                .map_err(|err| err.to_string())
+                        });
+                    (line, result)
+                }
+                Err(err) => (
+                    err.position().map_or(0, |pos| pos.line()),
+                    Err(err.to_string()),
+                ),
+            };
+            match result {
+                Ok(_) => report.inserted += 1,
+                Err(message) if strict => {
+                    return Err(format!("Line {}: {}; nothing was imported", line, message))
+                }
+                Err(message) => {
+                    report.skipped += 1;
+                    if report.errors.len() < MAX_REPORTED_ERRORS {
+                        report.errors.push(RowError { line, message });
+                    }
+                }
+            }
+        }
+    }
+    tx.commit().map_err(|err| err.to_string())?;
+    Ok(report)
+}
+
+/// Inserts the rows of a CSV file (header row required, `,` or `;`
+/// separated) into `table`, mapping CSV headers to columns with
+/// `column_map` as `(header, column)` pairs. Fields are converted to the
+/// column's declared type. Rows that fail conversion or a constraint are
+/// skipped and reported; with `strict`, the first one aborts the import and
+/// nothing is inserted.
+#[tauri::command]
+pub async fn import_csv(
+    app: AppHandle,
+    db_path: String,
+    table: String,
+    csv_path: String,
+    column_map: Vec<(String, String)>,
+    strict: bool,
+) -> Result<ImportReport, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    let csv_path = resolve_app_path(&app, &csv_path)?;
+    super::run_blocking(move || import(&db_path, &table, &csv_path, &column_map, strict)).await
+}
@@ -0,0 +1,372 @@
+//! Housekeeping commands for database files.
+
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// Result row of `PRAGMA wal_checkpoint`.
+#[derive(Serialize)]
+pub struct WalCheckpointStats {
+    pub busy: i64,
+    pub log: i64,
+    pub checkpointed: i64,
+}
+
+/// Flushes the WAL into the main file and truncates it, e.g. before the
+/// database folder is synced by Dropbox/OneDrive.
+#[tauri::command]
+pub async fn checkpoint_wal(app: AppHandle, db_path: String) -> Result<WalCheckpointStats, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || {
+        let conn = super::open(&db_path)?;
+        let (busy, log, checkpointed) = super::checkpoint_truncate(&conn)?;
+        if busy != 0 {
+            return Err(
+                "Database is busy; the checkpoint could not complete. Try again later".to_string(),
+            );
+        }
+        Ok(WalCheckpointStats {
+            busy,
+            log,
+            checkpointed,
+        })
+    })
+    .await
+}
+
+/// Clears the `-wal`/`-shm` files a crash left behind when they keep the
+/// database from opening. Returns `false` when there was nothing to clean up.
+///
+/// Whether another process still has the database open is checked by taking
+/// an exclusive lock, which fails even against idle WAL-mode connections; in
+/// that case the files are live and are left alone.
+#[tauri::command]
+pub async fn recover_stale_lock(app: AppHandle, db_path: String) -> Result<bool, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || {
+        let sidecars = [
+            super::sidecar_path(&db_path, "-wal"),
+            super::sidecar_path(&db_path, "-shm"),
+        ];
+        if !sidecars.iter().any(|path| path.exists()) {
+            return Ok(false);
+        }
+
+        let conn = super::open(&db_path)?;
+        conn.busy_timeout(Duration::ZERO)
+            .map_err(|err| err.to_string())?;
+        conn.pragma_update(None, "locking_mode", "EXCLUSIVE")
+            .map_err(|err| err.to_string())?;
+        // The lock is kept until the connection closes. Acquiring it also
+        // replays the WAL into the database, as opening after a crash would.
+        if let Err(err) = conn.execute_batch("BEGIN EXCLUSIVE; COMMIT;") {
+            return Err(if super::is_busy(&err) {
+                "Database is in use by another connection; close it and try again".to_string()
+            } else {
+                err.to_string()
+            });
+        }
+        let (busy, _, _) = super::checkpoint_truncate(&conn)?;
+        if busy != 0 {
+            return Err("Database is busy; the checkpoint could not complete".to_string());
+        }
+        drop(conn);
+
+        // Closing normally deletes the WAL; whatever remains is an orphan.
+        for path in &sidecars {
+            match std::fs::remove_file(path) {
+                Ok(()) => log::info!("Removed stale {}", path.display()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(format!("Failed to remove {}: {}", path.display(), err)),
+            }
+        }
+        Ok(true)
+    })
+    .await
+}
+
+/// One row of `PRAGMA foreign_key_check`.
+#[derive(Serialize)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+    pub fkid: i64,
+}
+
+#[derive(Serialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub integrity_errors: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+}
+
+fn integrity_report(conn: &Connection) -> rusqlite::Result<IntegrityReport> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let messages = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    // A healthy database yields exactly one row reading "ok".
+    let integrity_errors = if messages.len() == 1 && messages[0] == "ok" {
+        Vec::new()
+    } else {
+        messages
+    };
+
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let foreign_key_violations = stmt
+        .query_map([], |row| {
+            Ok(ForeignKeyViolation {
+                table: row.get(0)?,
+                rowid: row.get(1)?,
+                parent: row.get(2)?,
+                fkid: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(IntegrityReport {
+        ok: integrity_errors.is_empty() && foreign_key_violations.is_empty(),
+        integrity_errors,
+        foreign_key_violations,
+    })
+}
+
+pub(crate) fn check_integrity(db_path: &Path) -> Result<IntegrityReport, String> {
+    let conn = super::open(db_path)?;
+    integrity_report(&conn).map_err(|err| err.to_string())
+}
+
+/// Runs `PRAGMA integrity_check` and `PRAGMA foreign_key_check`, e.g. after a
+/// hard power-off, so the UI can recommend a restore.
+#[tauri::command]
+pub async fn check_database_integrity(
+    app: AppHandle,
+    db_path: String,
+) -> Result<IntegrityReport, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || check_integrity(&db_path)).await
+}
+
+const VACUUM_PROGRESS_EVENT: &str = "vacuum-progress";
+
+#[derive(Clone, Serialize)]
+struct VacuumProgress<'a> {
+    db_path: &'a str,
+    /// `"started"` or `"finished"`.
+    phase: &'a str,
+    error: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+pub struct VacuumStats {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    pub elapsed_ms: u64,
+}
+
+fn file_size(path: &Path) -> Result<u64, String> {
+    std::fs::metadata(path)
+        .map(|meta| meta.len())
+        .map_err(|err| err.to_string())
+}
+
+fn vacuum(db_path: &Path) -> Result<VacuumStats, String> {
+    let before_bytes = file_size(db_path)?;
+    let started = Instant::now();
+
+    let conn = super::open(db_path)?;
+    // VACUUM needs exclusive access; fail fast instead of waiting on a lock.
+    conn.busy_timeout(Duration::ZERO)
+        .map_err(|err| err.to_string())?;
+    conn.execute_batch("VACUUM").map_err(|err| {
+        if super::is_busy(&err) {
+            "Database is locked by another connection; close it and try again".to_string()
+        } else {
+            err.to_string()
+        }
+    })?;
+    // In WAL mode the rewritten pages land in the WAL first.
+    super::checkpoint_truncate(&conn)?;
+
+    Ok(VacuumStats {
+        before_bytes,
+        after_bytes: file_size(db_path)?,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Rebuilds the database file to reclaim space left by deletes. Emits
+/// `vacuum-progress` when it starts and when it finishes (successfully or not).
+#[tauri::command]
+pub async fn vacuum_database(app: AppHandle, db_path: String) -> Result<VacuumStats, String> {
+    let resolved = super::resolve_db_path(&app, &db_path)?;
+    let emit = |phase: &str, error: Option<&str>| {
+        let payload = VacuumProgress {
+            db_path: &db_path,
+            phase,
+            error,
+        };
+        if let Err(err) = app.emit(VACUUM_PROGRESS_EVENT, payload) {
+            log::warn!("Failed to emit {}: {}", VACUUM_PROGRESS_EVENT, err);
+        }
+    };
+
+    emit("started", None);
+    let result = super::run_blocking(move || vacuum(&resolved)).await;
+    emit("finished", result.as_ref().err().map(String::as_str));
+    result
+}
+
+/// Storage figures for one database file.
+#[derive(Serialize)]
+pub struct DbStats {
+    /// Main file plus its `-wal` and `-shm` companions.
+    pub total_bytes: u64,
+    pub page_count: i64,
+    pub page_size: i64,
+    pub modified_epoch_ms: i64,
+}
+
+fn sidecar_size(path: &Path) -> Result<u64, String> {
+    match std::fs::metadata(path) {
+        Ok(meta) => Ok(meta.len()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn stats(db_path: &Path) -> Result<DbStats, String> {
+    let meta = match std::fs::metadata(db_path) {
+        Ok(meta) => meta,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(format!("Database not found: {}", db_path.display()))
+        }
+        Err(err) => return Err(err.to_string()),
+    };
+    let modified = meta.modified().map_err(|err| err.to_string())?;
+    let modified_epoch_ms = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or(0);
+    let total_bytes = meta.len()
+        + sidecar_size(&super::sidecar_path(db_path, "-wal"))?
+        + sidecar_size(&super::sidecar_path(db_path, "-shm"))?;
+
+    let conn = super::open_readonly(db_path)?;
+    let pragma = |name: &str| {
+        conn.pragma_query_value(None, name, |row| row.get::<_, i64>(0))
+            .map_err(|err| err.to_string())
+    };
+    Ok(DbStats {
+        total_bytes,
+        page_count: pragma("page_count")?,
+        page_size: pragma("page_size")?,
+        modified_epoch_ms,
+    })
+}
+
+/// Size, page layout and last-modified time of a database, for the storage
+/// screen. Missing `-wal`/`-shm` files count as zero bytes.
+#[tauri::command]
+pub async fn database_stats(app: AppHandle, db_path: String) -> Result<DbStats, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || stats(&db_path)).await
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Pending,
+    UpToDate,
+    /// The database was written by a newer build than this one.
+    Ahead,
+}
+
+#[derive(Serialize)]
+pub struct MigrationStatus {
+    pub state: MigrationState,
+    pub current_version: i64,
+    pub expected_version: i64,
+}
+
+/// Compares the database's `user_version` with the schema version this build
+/// expects, without changing anything, so the UI can warn before migrating
+/// (or before a downgraded app opens a newer database).
+#[tauri::command]
+pub async fn check_migrations(
+    app: AppHandle,
+    db_path: String,
+    expected_version: i64,
+) -> Result<MigrationStatus, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    let current_version: i64 = super::run_blocking(move || {
+        super::open_readonly(&db_path)?
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|err| err.to_string())
+    })
+    .await?;
+    let state = match current_version.cmp(&expected_version) {
+        std::cmp::Ordering::Less => MigrationState::Pending,
+        std::cmp::Ordering::Equal => MigrationState::UpToDate,
+        std::cmp::Ordering::Greater => MigrationState::Ahead,
+    };
+    Ok(MigrationStatus {
+        state,
+        current_version,
+        expected_version,
+    })
+}
+
+/// What the first bytes of a database file say about it.
+#[derive(Serialize)]
+pub struct EncryptionStatus {
+    /// No SQLite header, but sized in whole pages as SQLCipher files are.
+    pub appears_encrypted: bool,
+    /// A plain SQLite file, an empty file (which SQLite opens as an empty
+    /// database) or one that appears encrypted.
+    pub is_sqlite: bool,
+    pub size_bytes: u64,
+}
+
+/// Smallest SQLite page size; SQLCipher files are always a multiple of it.
+const MIN_PAGE_SIZE: u64 = 512;
+
+pub(crate) fn encryption_status(db_path: &Path) -> Result<EncryptionStatus, String> {
+    let file = match std::fs::File::open(db_path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(format!("Database not found: {}", db_path.display()))
+        }
+        Err(err) => return Err(err.to_string()),
+    };
+    let size_bytes = file.metadata().map_err(|err| err.to_string())?.len();
+    let mut header = Vec::with_capacity(super::SQLITE_HEADER.len());
+    file.take(super::SQLITE_HEADER.len() as u64)
+        .read_to_end(&mut header)
+        .map_err(|err| err.to_string())?;
+
+    let plain = header == super::SQLITE_HEADER;
+    let appears_encrypted =
+        !plain && size_bytes >= MIN_PAGE_SIZE && size_bytes % MIN_PAGE_SIZE == 0;
+    Ok(EncryptionStatus {
+        appears_encrypted,
+        is_sqlite: size_bytes == 0 || plain || appears_encrypted,
+        size_bytes,
+    })
+}
+
+/// Reports from the file header whether a database is encrypted (e.g. with
+/// SQLCipher) without opening it, for compliance checks.
+#[tauri::command]
+pub async fn database_encryption_status(
+    app: AppHandle,
+    db_path: String,
+) -> Result<EncryptionStatus, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || encryption_status(&db_path)).await
+}
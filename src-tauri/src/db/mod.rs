@@ -0,0 +1,196 @@
+//! Direct SQLite access for maintenance work the SQL plugin doesn't cover.
+//! Day-to-day queries still go through `tauri_plugin_sql` from the frontend.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags};
+use tauri::{AppHandle, Manager};
+
+pub mod archive;
+pub mod auto_backup;
+pub mod backup;
+pub mod import;
+pub mod maintenance;
+pub mod query;
+pub mod search;
+pub mod template;
+
+/// Connection URL of the primary database in its default location. Once the
+/// data dir is relocated the frontend loads [`primary_db_url`] instead.
+pub const PRIMARY_DB_URL: &str = "sqlite:jurisdesk.db";
+pub const PRIMARY_DB_FILE: &str = "jurisdesk.db";
+
+/// First 16 bytes of every unencrypted SQLite database file.
+pub const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long quitting waits for the final checkpoint.
+const EXIT_CHECKPOINT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The primary database: in the relocated data dir if the data was moved,
+/// otherwise where `tauri_plugin_sql` puts [`PRIMARY_DB_URL`] (app config dir).
+pub fn primary_db_path(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(dir) = crate::paths::relocated_data_dir() {
+        return Ok(dir.join(PRIMARY_DB_FILE));
+    }
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(PRIMARY_DB_FILE))
+        .map_err(|err| err.to_string())
+}
+
+/// The connection URL for [`primary_db_path`]. The SQL plugin joins the part
+/// after `sqlite:` onto the config dir, so an absolute path is used as is.
+pub fn primary_db_url(app: &AppHandle) -> Result<String, String> {
+    if crate::paths::relocated_data_dir().is_none() {
+        return Ok(PRIMARY_DB_URL.to_string());
+    }
+    let path = primary_db_path(app)?;
+    path.to_str()
+        .map(|path| format!("sqlite:{}", path))
+        .ok_or_else(|| format!("Database path is not valid UTF-8: {}", path.display()))
+}
+
+/// Resolves a `db_path` argument from the webview. [`PRIMARY_DB_URL`] and
+/// [`primary_db_url`] stand for the primary database wherever it is;
+/// anything else is a database file relative to the app data dir.
+pub fn resolve_db_path(app: &AppHandle, db_path: &str) -> Result<PathBuf, String> {
+    let db_path = db_path.trim();
+    if db_path == PRIMARY_DB_URL || db_path == primary_db_url(app)? {
+        return primary_db_path(app);
+    }
+    crate::paths::resolve_app_path(app, db_path)
+}
+
+/// Whether the SQL plugin currently holds an open pool for the primary
+/// database, under its default or its relocated URL.
+pub async fn primary_db_is_open(app: &AppHandle) -> bool {
+    let Some(instances) = app.try_state::<tauri_plugin_sql::DbInstances>() else {
+        return false;
+    };
+    let urls = [Some(PRIMARY_DB_URL.to_string()), primary_db_url(app).ok()];
+    let instances = instances.0.read().await;
+    urls.iter().flatten().any(|url| {
+        matches!(
+            instances.get(url),
+            Some(tauri_plugin_sql::DbPool::Sqlite(pool)) if !pool.is_closed()
+        )
+    })
+}
+
+/// Path of a `-wal`/`-shm` companion file.
+pub fn sidecar_path(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Runs blocking SQLite/file work off the main thread.
+pub async fn run_blocking<T, F>(task: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+/// Opens an existing database read-write; never creates a new file.
+pub fn open(path: &Path) -> Result<Connection, String> {
+    if !path.is_file() {
+        return Err(format!("Database not found: {}", path.display()));
+    }
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|err| err.to_string())?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|err| err.to_string())?;
+    Ok(conn)
+}
+
+/// Opens an existing database read-only at the engine level: the file is
+/// opened with `SQLITE_OPEN_READONLY` and `query_only` is set, so any write
+/// attempt fails inside SQLite itself.
+pub fn open_readonly(path: &Path) -> Result<Connection, String> {
+    if !path.is_file() {
+        return Err(format!("Database not found: {}", path.display()));
+    }
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|err| err.to_string())?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|err| err.to_string())?;
+    conn.pragma_update(None, "query_only", true)
+        .map_err(|err| err.to_string())?;
+    Ok(conn)
+}
+
+/// Accepts plain SQL identifiers (`[A-Za-z_][A-Za-z0-9_]*`) so table and
+/// column names from the webview can be spliced into SQL without quoting games.
+pub fn validate_identifier(name: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_');
+    if !valid_start || !chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_') {
+        return Err(format!("Invalid identifier '{}'", name));
+    }
+    Ok(())
+}
+
+/// Whether SQLite failed because another connection holds a lock.
+pub fn is_busy(err: &rusqlite::Error) -> bool {
+    matches!(
+        err.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Runs `PRAGMA wal_checkpoint(TRUNCATE)` and returns `(busy, log, checkpointed)`.
+pub fn checkpoint_truncate(conn: &Connection) -> Result<(i64, i64, i64), String> {
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })
+    .map_err(|err| err.to_string())
+}
+
+/// Folds the primary database's WAL back into the main file before the
+/// process exits, so a synced data folder isn't left with a `-wal` file.
+/// Gives up after a short timeout rather than holding up the quit.
+pub fn checkpoint_on_exit(app: &AppHandle) {
+    let path = match primary_db_path(app) {
+        Ok(path) if path.is_file() => path,
+        Ok(_) => return,
+        Err(err) => {
+            log::warn!("Skipping exit checkpoint: {}", err);
+            return;
+        }
+    };
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let result = open(&path).and_then(|conn| {
+            conn.busy_timeout(EXIT_CHECKPOINT_TIMEOUT)
+                .map_err(|err| err.to_string())?;
+            checkpoint_truncate(&conn)
+        });
+        let _ = tx.send(result);
+    });
+
+    match rx.recv_timeout(EXIT_CHECKPOINT_TIMEOUT) {
+        Ok(Ok((0, _, _))) => {}
+        Ok(Ok(_)) => log::warn!("Exit checkpoint incomplete: database is busy"),
+        Ok(Err(err)) => log::warn!("Exit checkpoint failed: {}", err),
+        Err(_) => log::warn!(
+            "Exit checkpoint timed out after {:?}",
+            EXIT_CHECKPOINT_TIMEOUT
+        ),
+    }
+}
@@ -0,0 +1,789 @@
+//! Parameterized queries issued from Rust instead of through the SQL plugin,
+//! for work that needs streaming or engine-level guarantees.
+
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::CharIndices;
+
+use base64::Engine as _;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Batch, Connection, Statement, TransactionBehavior};
+use tauri::AppHandle;
+
+use crate::paths::resolve_save_path;
+
+/// A result row keyed by column name.
+pub type Row = serde_json::Map<String, serde_json::Value>;
+
+/// Maps JSON parameters onto SQLite bindings: null, booleans (as 0/1),
+/// integers, floats and strings. Arrays and objects are rejected.
+pub fn bind_params(params: &[serde_json::Value]) -> Result<Vec<Value>, String> {
+    params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| match param {
+            serde_json::Value::Null => Ok(Value::Null),
+            serde_json::Value::Bool(flag) => Ok(Value::Integer(i64::from(*flag))),
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(integer) => Ok(Value::Integer(integer)),
+                None => number
+                    .as_f64()
+                    .map(Value::Real)
+                    .ok_or_else(|| format!("Parameter {} is out of range", index + 1)),
+            },
+            serde_json::Value::String(text) => Ok(Value::Text(text.clone())),
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => Err(format!(
+                "Parameter {} must be null, a boolean, a number or a string",
+                index + 1
+            )),
+        })
+        .collect()
+}
+
+/// First keyword of `sql`, uppercased, skipping whitespace and comments.
+pub fn leading_keyword(sql: &str) -> Option<String> {
+    let mut rest = sql;
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, tail)| tail);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, tail)| tail);
+        } else {
+            break;
+        }
+    }
+    let keyword: String = rest
+        .chars()
+        .take_while(|ch| ch.is_ascii_alphabetic())
+        .collect();
+    (!keyword.is_empty()).then(|| keyword.to_ascii_uppercase())
+}
+
+pub fn ensure_select(sql: &str) -> Result<(), String> {
+    match leading_keyword(sql).as_deref() {
+        Some("SELECT") => Ok(()),
+        Some(other) => Err(format!("Only SELECT statements are allowed, got {}", other)),
+        None => Err("SQL statement is empty".to_string()),
+    }
+}
+
+/// Prepares exactly one statement; `Connection::prepare` would silently
+/// ignore anything after the first `;`.
+pub fn prepare_single<'conn>(
+    conn: &'conn Connection,
+    sql: &str,
+) -> Result<Statement<'conn>, String> {
+    let mut batch = Batch::new(conn, sql);
+    let stmt = batch
+        .next()
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| "SQL statement is empty".to_string())?;
+    if batch.next().map_err(|err| err.to_string())?.is_some() {
+        return Err("Only a single SQL statement is allowed".to_string());
+    }
+    Ok(stmt)
+}
+
+/// Prepares a single `SELECT`, double-checking with SQLite that it can't write.
+pub fn prepare_select<'conn>(
+    conn: &'conn Connection,
+    sql: &str,
+) -> Result<Statement<'conn>, String> {
+    ensure_select(sql)?;
+    let stmt = prepare_single(conn, sql)?;
+    if !stmt.readonly() {
+        return Err("Statement would modify the database".to_string());
+    }
+    Ok(stmt)
+}
+
+/// Same JSON shapes `tauri_plugin_sql` produces, so rows look identical to
+/// the frontend whichever path they came through (blobs are byte arrays).
+pub fn value_to_json(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(integer) => integer.into(),
+        ValueRef::Real(real) => real.into(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned().into(),
+        ValueRef::Blob(blob) => blob
+            .iter()
+            .map(|byte| serde_json::Value::from(*byte))
+            .collect(),
+    }
+}
+
+/// Executes a prepared query and collects every row as a JSON object.
+pub fn collect_rows(stmt: &mut Statement<'_>, params: Vec<Value>) -> Result<Vec<Row>, String> {
+    collect_rows_up_to(stmt, params, usize::MAX)
+}
+
+/// Like [`collect_rows`], but stops after `max_rows` rows.
+fn collect_rows_up_to(
+    stmt: &mut Statement<'_>,
+    params: Vec<Value>,
+    max_rows: usize,
+) -> Result<Vec<Row>, String> {
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt
+        .query(rusqlite::params_from_iter(params))
+        .map_err(|err| err.to_string())?;
+    let mut collected = Vec::new();
+    while collected.len() < max_rows {
+        let Some(row) = rows.next().map_err(|err| err.to_string())? else {
+            break;
+        };
+        let mut object = Row::new();
+        for (index, column) in columns.iter().enumerate() {
+            let value = row.get_ref(index).map_err(|err| err.to_string())?;
+            object.insert(column.clone(), value_to_json(value));
+        }
+        collected.push(object);
+    }
+    Ok(collected)
+}
+
+fn csv_escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn csv_field(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(integer) => integer.to_string(),
+        ValueRef::Real(real) => real.to_string(),
+        ValueRef::Text(text) => csv_escape(&String::from_utf8_lossy(text)).into_owned(),
+        ValueRef::Blob(blob) => base64::engine::general_purpose::STANDARD.encode(blob),
+    }
+}
+
+fn write_csv(
+    db_path: &Path,
+    sql: &str,
+    params: &[serde_json::Value],
+    dest_path: &Path,
+) -> Result<u64, String> {
+    let conn = super::open_readonly(db_path)?;
+    let mut stmt = prepare_select(&conn, sql)?;
+    let params = bind_params(params)?;
+    let header: Vec<String> = stmt
+        .column_names()
+        .into_iter()
+        .map(|name| csv_escape(name).into_owned())
+        .collect();
+    let column_count = header.len();
+
+    let mut out = BufWriter::new(File::create(dest_path).map_err(|err| err.to_string())?);
+    writeln!(out, "{}", header.join(",")).map_err(|err| err.to_string())?;
+
+    let mut rows = stmt
+        .query(rusqlite::params_from_iter(params))
+        .map_err(|err| err.to_string())?;
+    let mut written = 0u64;
+    while let Some(row) = rows.next().map_err(|err| err.to_string())? {
+        let mut fields = Vec::with_capacity(column_count);
+        for index in 0..column_count {
+            fields.push(csv_field(
+                row.get_ref(index).map_err(|err| err.to_string())?,
+            ));
+        }
+        writeln!(out, "{}", fields.join(",")).map_err(|err| err.to_string())?;
+        written += 1;
+    }
+    out.flush().map_err(|err| err.to_string())?;
+    Ok(written)
+}
+
+/// Streams the rows of a `SELECT` into a CSV file (header row = column names,
+/// NULL = empty field) and returns how many rows were written. `dest_path` is
+/// relative to the app data dir or comes from `prompt_save_path`; `db_path`
+/// is resolved by `resolve_db_path`.
+#[tauri::command]
+pub async fn export_query_csv(
+    app: AppHandle,
+    db_path: String,
+    sql: String,
+    params: Vec<serde_json::Value>,
+    dest_path: String,
+) -> Result<u64, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    let dest_path = resolve_save_path(&app, &dest_path)?;
+    super::run_blocking(move || {
+        // A failed export must not destroy a file that was already there.
+        let tmp = super::sidecar_path(&dest_path, ".partial");
+        let result = write_csv(&db_path, &sql, &params, &tmp).and_then(|written| {
+            fs::rename(&tmp, &dest_path)
+                .map(|()| written)
+                .map_err(|err| err.to_string())
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    })
+    .await
+}
+
+/// Runs a `SELECT` on a read-only connection, so reporting screens can't
+/// mutate case data even by accident.
+#[tauri::command]
+pub async fn run_readonly_query(
+    app: AppHandle,
+    db_path: String,
+    sql: String,
+    params: Vec<serde_json::Value>,
+) -> Result<Vec<Row>, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || {
+        let conn = super::open_readonly(&db_path)?;
+        let mut stmt = prepare_select(&conn, &sql)?;
+        collect_rows(&mut stmt, bind_params(&params)?)
+    })
+    .await
+}
+
+/// Largest page `query_page` returns.
+const MAX_PAGE_SIZE: u64 = 10_000;
+
+#[derive(serde::Serialize)]
+pub struct QueryPage {
+    pub rows: Vec<Row>,
+    /// Whether rows exist past this page.
+    pub has_more: bool,
+}
+
+/// A lexical token of SQLite SQL. Comments and whitespace are dropped;
+/// strings, numbers and quoted identifiers can never be mistaken for keywords.
+#[derive(Clone, Copy, PartialEq)]
+enum Token<'sql> {
+    /// A bare keyword or identifier.
+    Word(&'sql str),
+    /// `"name"`, `` `name` `` or `[name]`.
+    QuotedIdentifier,
+    /// A string, blob or numeric literal.
+    Literal,
+    Symbol(char),
+}
+
+/// Consumes characters up to and including `close`.
+fn skip_past(chars: &mut Peekable<CharIndices<'_>>, close: char, what: &str) -> Result<(), String> {
+    for (_, ch) in chars.by_ref() {
+        if ch == close {
+            return Ok(());
+        }
+    }
+    Err(format!("Unterminated {}", what))
+}
+
+/// Splits `sql` into tokens the way SQLite's own tokenizer does, as far as
+/// telling keywords apart from everything else requires.
+fn tokenize(sql: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        match ch {
+            _ if ch.is_whitespace() => {}
+            '-' if chars.peek().is_some_and(|&(_, next)| next == '-') => {
+                // A comment may end the input without a newline.
+                let _ = skip_past(&mut chars, '\n', "comment");
+            }
+            '/' if chars.peek().is_some_and(|&(_, next)| next == '*') => {
+                chars.next();
+                let mut previous = '\0';
+                let mut closed = false;
+                for (_, inner) in chars.by_ref() {
+                    if previous == '*' && inner == '/' {
+                        closed = true;
+                        break;
+                    }
+                    previous = inner;
+                }
+                if !closed {
+                    return Err("Unterminated comment".to_string());
+                }
+            }
+            // SQLite escapes a quote by doubling it, which comes out here as
+            // two adjacent tokens of the same kind.
+            '\'' => {
+                skip_past(&mut chars, '\'', "string")?;
+                tokens.push(Token::Literal);
+            }
+            '"' | '`' | '[' => {
+                let close = if ch == '[' { ']' } else { ch };
+                skip_past(&mut chars, close, "quoted identifier")?;
+                tokens.push(Token::QuotedIdentifier);
+            }
+            _ if ch.is_ascii_digit()
+                || (ch == '.' && chars.peek().is_some_and(|&(_, next)| next.is_ascii_digit())) =>
+            {
+                while chars
+                    .peek()
+                    .is_some_and(|&(_, next)| next.is_ascii_alphanumeric() || next == '.')
+                {
+                    chars.next();
+                }
+                tokens.push(Token::Literal);
+            }
+            _ if ch.is_alphabetic() || ch == '_' => {
+                let mut end = start + ch.len_utf8();
+                while let Some(&(index, next)) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_' || next == '$') {
+                        break;
+                    }
+                    end = index + next.len_utf8();
+                    chars.next();
+                }
+                // X'..' is a blob literal, not the identifier X.
+                let word = &sql[start..end];
+                if word.eq_ignore_ascii_case("x")
+                    && chars.peek().is_some_and(|&(_, next)| next == '\'')
+                {
+                    chars.next();
+                    skip_past(&mut chars, '\'', "blob literal")?;
+                    tokens.push(Token::Literal);
+                } else {
+                    tokens.push(Token::Word(word));
+                }
+            }
+            _ => tokens.push(Token::Symbol(ch)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Whether `sql` has a `LIMIT` outside any parentheses, string, quoted
+/// identifier or comment, i.e. one that applies to the statement itself.
+fn has_top_level_limit(sql: &str) -> bool {
+    let Ok(tokens) = tokenize(sql) else {
+        // Preparing the statement reports the syntax error.
+        return false;
+    };
+    let mut depth = 0usize;
+    tokens.iter().any(|token| match token {
+        Token::Symbol('(') => {
+            depth += 1;
+            false
+        }
+        Token::Symbol(')') => {
+            depth = depth.saturating_sub(1);
+            false
+        }
+        Token::Word(word) => depth == 0 && word.eq_ignore_ascii_case("LIMIT"),
+        _ => false,
+    })
+}
+
+fn fetch_page(
+    db_path: &Path,
+    sql: &str,
+    params: &[serde_json::Value],
+    offset: u64,
+    limit: u64,
+) -> Result<QueryPage, String> {
+    let sql = sql.trim_end().trim_end_matches(';');
+    if has_top_level_limit(sql) {
+        return Err(
+            "Query already has a LIMIT; remove it and page with offset/limit instead".to_string(),
+        );
+    }
+    let offset = i64::try_from(offset).map_err(|_| "Offset is too large".to_string())?;
+    let conn = super::open_readonly(db_path)?;
+    // Validated on its own first, so nothing can escape the subquery below.
+    let param_count = prepare_select(&conn, sql)?.parameter_count();
+
+    // The newline keeps a trailing `--` comment from swallowing the `)`.
+    let paged = format!(
+        "SELECT * FROM ({}\n) LIMIT ?{} OFFSET ?{}",
+        sql,
+        param_count + 1,
+        param_count + 2
+    );
+    let mut stmt = prepare_select(&conn, &paged)?;
+    let mut values = bind_params(params)?;
+    if values.len() != param_count {
+        return Err(format!(
+            "Query expects {} parameter(s), got {}",
+            param_count,
+            values.len()
+        ));
+    }
+    // One extra row tells whether there is a next page.
+    values.push(Value::Integer(limit as i64 + 1));
+    values.push(Value::Integer(offset));
+    let mut rows = collect_rows(&mut stmt, values)?;
+    let has_more = rows.len() as u64 > limit;
+    rows.truncate(limit as usize);
+    Ok(QueryPage { rows, has_more })
+}
+
+/// Returns `limit` rows (at most 10 000) of a read-only `SELECT`, starting at
+/// `offset`, so large results can be loaded page by page instead of in one
+/// IPC message. The statement must not have its own `LIMIT`.
+#[tauri::command]
+pub async fn query_page(
+    app: AppHandle,
+    db_path: String,
+    sql: String,
+    params: Vec<serde_json::Value>,
+    offset: u64,
+    limit: u64,
+) -> Result<QueryPage, String> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(format!("limit must be between 1 and {}", MAX_PAGE_SIZE));
+    }
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || fetch_page(&db_path, &sql, &params, offset, limit)).await
+}
+
+/// Most rows `run_safe_query` returns; larger results need a `LIMIT`.
+const MAX_SAFE_QUERY_ROWS: usize = 5_000;
+
+/// Keywords that only occur in statements that write, change the schema or
+/// reach outside the database, never in a plain query.
+const FORBIDDEN_KEYWORDS: &[&str] = &[
+    "ALTER",
+    "ANALYZE",
+    "ATTACH",
+    "BEGIN",
+    "COMMIT",
+    "CREATE",
+    "DELETE",
+    "DETACH",
+    "DROP",
+    "INSERT",
+    "PRAGMA",
+    "REINDEX",
+    "RELEASE",
+    "REPLACE",
+    "ROLLBACK",
+    "SAVEPOINT",
+    "UPDATE",
+    "VACUUM",
+];
+
+/// Checks token by token that `sql` is a single `SELECT` (optionally with a
+/// `WITH` clause), naming the first construct that isn't allowed.
+fn ensure_safe_select(sql: &str) -> Result<(), String> {
+    let tokens = tokenize(sql)?;
+    let mut statement = tokens.as_slice();
+    while let [rest @ .., Token::Symbol(';')] = statement {
+        statement = rest;
+    }
+    if statement.contains(&Token::Symbol(';')) {
+        return Err("Multiple statements are not allowed".to_string());
+    }
+    match statement.first() {
+        None => return Err("SQL statement is empty".to_string()),
+        Some(Token::Word(word))
+            if word.eq_ignore_ascii_case("SELECT") || word.eq_ignore_ascii_case("WITH") => {}
+        Some(Token::Word(word)) => {
+            return Err(format!(
+                "Only SELECT queries are allowed, got {}",
+                word.to_ascii_uppercase()
+            ))
+        }
+        Some(_) => return Err("Only SELECT queries are allowed".to_string()),
+    }
+    for (index, token) in statement.iter().enumerate() {
+        let Token::Word(word) = token else {
+            continue;
+        };
+        let keyword = word.to_ascii_uppercase();
+        // `replace(...)` is a string function, not REPLACE INTO.
+        let is_call = statement.get(index + 1) == Some(&Token::Symbol('('));
+        if keyword.starts_with("PRAGMA_") {
+            return Err(format!("PRAGMA functions are not allowed: {}", word));
+        }
+        if is_call && keyword == "LOAD_EXTENSION" {
+            return Err("load_extension is not allowed".to_string());
+        }
+        if !is_call && FORBIDDEN_KEYWORDS.contains(&keyword.as_str()) {
+            return Err(format!("{} is not allowed in a query", keyword));
+        }
+    }
+    Ok(())
+}
+
+fn safe_query(db_path: &Path, sql: &str) -> Result<Vec<Row>, String> {
+    ensure_safe_select(sql)?;
+    let conn = super::open_readonly(db_path)?;
+    // SQLite has the last word on whether the statement can write.
+    let mut stmt = prepare_single(&conn, sql)?;
+    if !stmt.readonly() {
+        return Err("Statement would modify the database".to_string());
+    }
+    let rows = collect_rows_up_to(&mut stmt, Vec::new(), MAX_SAFE_QUERY_ROWS + 1)?;
+    if rows.len() > MAX_SAFE_QUERY_ROWS {
+        return Err(format!(
+            "Query returned more than {} rows; add a LIMIT",
+            MAX_SAFE_QUERY_ROWS
+        ));
+    }
+    Ok(rows)
+}
+
+/// Runs SQL typed into the advanced query console. Only a single `SELECT` is
+/// accepted: other statements, `ATTACH`, `PRAGMA` and `load_extension` are
+/// refused with an error naming them, the connection is read-only, and more
+/// than 5 000 result rows is an error.
+#[tauri::command]
+pub async fn run_safe_query(
+    app: AppHandle,
+    db_path: String,
+    sql: String,
+) -> Result<Vec<Row>, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || safe_query(&db_path, &sql)).await
+}
+
+fn query_with_attach(
+    main_db: &Path,
+    attachments: &[(std::path::PathBuf, String)],
+    sql: &str,
+    params: &[serde_json::Value],
+) -> Result<Vec<Row>, String> {
+    // Attached databases inherit the main connection's read-only flag.
+    let conn = super::open_readonly(main_db)?;
+    let mut attached = Vec::new();
+    let result = (|| {
+        for (path, alias) in attachments {
+            if !path.is_file() {
+                return Err(format!("Database not found: {}", path.display()));
+            }
+            conn.execute(
+                &format!("ATTACH DATABASE ?1 AS {}", alias),
+                [path.to_string_lossy()],
+            )
+            .map_err(|err| format!("Failed to attach '{}': {}", alias, err))?;
+            attached.push(alias.as_str());
+        }
+        let mut stmt = prepare_select(&conn, sql)?;
+        collect_rows(&mut stmt, bind_params(params)?)
+    })();
+
+    for alias in attached {
+        if let Err(err) = conn.execute_batch(&format!("DETACH DATABASE {}", alias)) {
+            log::warn!("Failed to detach '{}': {}", alias, err);
+        }
+    }
+    result
+}
+
+/// Runs a read-only `SELECT` that can reference other databases through the
+/// given `(path, alias)` pairs, e.g. `SELECT ... FROM archive.cases`. Each
+/// attachment is opened read-only and detached again afterwards.
+#[tauri::command]
+pub async fn run_query_with_attach(
+    app: AppHandle,
+    main_db: String,
+    attachments: Vec<(String, String)>,
+    sql: String,
+    params: Vec<serde_json::Value>,
+) -> Result<Vec<Row>, String> {
+    let main_db = super::resolve_db_path(&app, &main_db)?;
+    let mut resolved = Vec::with_capacity(attachments.len());
+    for (path, alias) in attachments {
+        super::validate_identifier(&alias)?;
+        if alias.eq_ignore_ascii_case("main") || alias.eq_ignore_ascii_case("temp") {
+            return Err(format!("Alias '{}' is reserved", alias));
+        }
+        if resolved
+            .iter()
+            .any(|(_, seen): &(_, String)| seen.eq_ignore_ascii_case(&alias))
+        {
+            return Err(format!("Alias '{}' is used more than once", alias));
+        }
+        resolved.push((super::resolve_db_path(&app, &path)?, alias));
+    }
+    super::run_blocking(move || query_with_attach(&main_db, &resolved, &sql, &params)).await
+}
+
+/// Statements that would end or nest the batch's own transaction.
+const TRANSACTION_KEYWORDS: &[&str] =
+    &["BEGIN", "COMMIT", "END", "ROLLBACK", "SAVEPOINT", "RELEASE"];
+
+fn execute_all(
+    db_path: &Path,
+    statements: &[(String, Vec<serde_json::Value>)],
+) -> Result<Vec<u64>, String> {
+    let mut conn = super::open(db_path)?;
+    // IMMEDIATE takes the write lock up front instead of failing midway
+    // when another connection is writing.
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|err| err.to_string())?;
+    let mut affected = Vec::with_capacity(statements.len());
+    for (index, (sql, params)) in statements.iter().enumerate() {
+        let fail = |err: String| format!("Statement {} failed: {}", index + 1, err);
+        if let Some(keyword) = leading_keyword(sql) {
+            if TRANSACTION_KEYWORDS.contains(&keyword.as_str()) {
+                return Err(fail(format!(
+                    "{} is not allowed inside a transaction",
+                    keyword
+                )));
+            }
+        }
+        let mut stmt = prepare_single(&tx, sql).map_err(fail)?;
+        let params = bind_params(params).map_err(fail)?;
+        let count = stmt
+            .execute(rusqlite::params_from_iter(params))
+            .map_err(|err| fail(err.to_string()))?;
+        affected.push(count as u64);
+    }
+    tx.commit().map_err(|err| err.to_string())?;
+    Ok(affected)
+}
+
+/// Runs parameterized write statements in one transaction and returns the
+/// affected-row count of each. If any statement fails nothing is committed.
+#[tauri::command]
+pub async fn execute_transaction(
+    app: AppHandle,
+    db_path: String,
+    statements: Vec<(String, Vec<serde_json::Value>)>,
+) -> Result<Vec<u64>, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || execute_all(&db_path, &statements)).await
+}
+
+#[derive(serde::Serialize)]
+pub struct ExecResult {
+    pub rows_affected: u64,
+    /// Rowid of the row the statement inserted; 0 when it inserted none or
+    /// the table has no rowid (`WITHOUT ROWID`).
+    pub last_insert_rowid: i64,
+}
+
+fn execute_one(
+    db_path: &Path,
+    sql: &str,
+    params: &[serde_json::Value],
+) -> Result<ExecResult, String> {
+    if let Some(keyword) = leading_keyword(sql) {
+        if TRANSACTION_KEYWORDS.contains(&keyword.as_str()) {
+            return Err(format!("{} is not allowed here", keyword));
+        }
+    }
+    // A fresh connection starts with a last rowid of 0, so anything else
+    // afterwards came from this statement.
+    let conn = super::open(db_path)?;
+    let mut stmt = prepare_single(&conn, sql)?;
+    if stmt.readonly() {
+        return Err("Statement doesn't write; use run_readonly_query instead".to_string());
+    }
+    let rows_affected = stmt
+        .execute(rusqlite::params_from_iter(bind_params(params)?))
+        .map_err(|err| err.to_string())?;
+    Ok(ExecResult {
+        rows_affected: rows_affected as u64,
+        last_insert_rowid: conn.last_insert_rowid(),
+    })
+}
+
+/// Runs one parameterized write and returns how many rows it changed and,
+/// for an `INSERT`, the new row's id, saving a second query for it.
+#[tauri::command]
+pub async fn execute_returning_id(
+    app: AppHandle,
+    db_path: String,
+    sql: String,
+    params: Vec<serde_json::Value>,
+) -> Result<ExecResult, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || execute_one(&db_path, &sql, &params)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_in_comments_strings_and_quoted_names_are_ignored() {
+        for sql in [
+            "SELECT 1 -- DROP TABLE clients",
+            "SELECT 1 /* DELETE FROM clients; */",
+            "SELECT 'DROP TABLE clients; --' AS note",
+            "SELECT 'it''s; DELETE' FROM t",
+            "SELECT \"delete\", `update`, [insert] FROM t",
+            "SELECT X'44524F50' FROM t",
+            "SELECT replace(name, 'a', 'b') FROM clients",
+        ] {
+            assert_eq!(ensure_safe_select(sql), Ok(()), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn rejects_writes_behind_a_with_clause() {
+        assert_eq!(
+            ensure_safe_select("WITH c AS (SELECT 1) DELETE FROM t").unwrap_err(),
+            "DELETE is not allowed in a query"
+        );
+        assert_eq!(
+            ensure_safe_select("WITH c AS (SELECT 1) SELECT * FROM c"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_multiple_statements_but_allows_trailing_semicolons() {
+        assert_eq!(
+            ensure_safe_select("SELECT 1; DROP TABLE t").unwrap_err(),
+            "Multiple statements are not allowed"
+        );
+        assert_eq!(
+            ensure_safe_select("SELECT 1; SELECT 2").unwrap_err(),
+            "Multiple statements are not allowed"
+        );
+        assert_eq!(ensure_safe_select("SELECT 1;;"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_pragma_and_attach() {
+        assert_eq!(
+            ensure_safe_select("PRAGMA user_version").unwrap_err(),
+            "Only SELECT queries are allowed, got PRAGMA"
+        );
+        assert_eq!(
+            ensure_safe_select("ATTACH 'other.db' AS other").unwrap_err(),
+            "Only SELECT queries are allowed, got ATTACH"
+        );
+        assert_eq!(
+            ensure_safe_select("SELECT * FROM pragma_table_info('clients')").unwrap_err(),
+            "PRAGMA functions are not allowed: pragma_table_info"
+        );
+        assert_eq!(
+            ensure_safe_select("SELECT load_extension('evil')").unwrap_err(),
+            "load_extension is not allowed"
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_tokens() {
+        assert_eq!(
+            ensure_safe_select("SELECT 'open").unwrap_err(),
+            "Unterminated string"
+        );
+        assert_eq!(
+            ensure_safe_select("SELECT 1 /* open").unwrap_err(),
+            "Unterminated comment"
+        );
+    }
+
+    #[test]
+    fn finds_only_top_level_limits() {
+        assert!(has_top_level_limit("SELECT * FROM t LIMIT 5"));
+        assert!(has_top_level_limit(
+            "SELECT * FROM (SELECT 1 LIMIT 1) limit 10"
+        ));
+        assert!(!has_top_level_limit("SELECT * FROM (SELECT 1 LIMIT 1)"));
+        assert!(!has_top_level_limit("SELECT 'LIMIT 5' FROM t"));
+        assert!(!has_top_level_limit("SELECT \"limit\" FROM t -- LIMIT 5"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Full-text search over a table through an FTS5 index.
+//!
+//! FTS5 is compiled into SQLite by rusqlite's `bundled` feature; a build
+//! linked against a system SQLite without it gets a clear error instead of
+//! "no such module".
+
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use super::validate_identifier;
+
+const MAX_RESULTS: u32 = 500;
+/// Tokens of context on each side of the match in a snippet.
+const SNIPPET_TOKENS: u32 = 12;
+
+#[derive(Serialize)]
+pub struct SearchHit {
+    pub rowid: i64,
+    /// Matched terms are wrapped in `[` and `]`. Document text is not escaped.
+    pub snippet: String,
+    /// bm25 score; lower is a better match.
+    pub rank: f64,
+}
+
+fn fts_table(source_table: &str) -> String {
+    format!("{}_fts", source_table)
+}
+
+fn ensure_fts5(conn: &Connection) -> Result<(), String> {
+    let enabled: bool = conn
+        .query_row(
+            "SELECT sqlite_compileoption_used('ENABLE_FTS5')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|err| err.to_string())?;
+    if !enabled {
+        return Err("Full-text search is unavailable: this SQLite build lacks FTS5".to_string());
+    }
+    Ok(())
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM pragma_table_info(?1)")
+        .map_err(|err| err.to_string())?;
+    let columns = stmt
+        .query_map([table], |row| row.get(0))
+        .map_err(|err| err.to_string())?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|err| err.to_string())?;
+    Ok(columns)
+}
+
+/// (Re)creates `<table>_fts` as an external-content FTS5 index over `columns`
+/// plus the triggers that keep it in sync, then rebuilds it from the table.
+fn create_index(db_path: &Path, source_table: &str, columns: &[String]) -> Result<(), String> {
+    validate_identifier(source_table)?;
+    if columns.is_empty() {
+        return Err("At least one column is required".to_string());
+    }
+    for column in columns {
+        validate_identifier(column)?;
+    }
+
+    let mut conn = super::open(db_path)?;
+    ensure_fts5(&conn)?;
+    let existing = table_columns(&conn, source_table)?;
+    if existing.is_empty() {
+        return Err(format!("Table '{}' does not exist", source_table));
+    }
+    if let Some(missing) = columns.iter().find(|column| !existing.contains(column)) {
+        return Err(format!(
+            "Column '{}' does not exist in '{}'",
+            missing, source_table
+        ));
+    }
+
+    let fts = fts_table(source_table);
+    let cols = columns.join(", ");
+    let new_cols = columns
+        .iter()
+        .map(|column| format!("new.{}", column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let old_cols = columns
+        .iter()
+        .map(|column| format!("old.{}", column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "DROP TRIGGER IF EXISTS {fts}_ai;
+         DROP TRIGGER IF EXISTS {fts}_ad;
+         DROP TRIGGER IF EXISTS {fts}_au;
+         DROP TABLE IF EXISTS {fts};
+         CREATE VIRTUAL TABLE {fts} USING fts5({cols}, content='{src}', content_rowid='rowid');
+         CREATE TRIGGER {fts}_ai AFTER INSERT ON {src} BEGIN
+           INSERT INTO {fts}(rowid, {cols}) VALUES (new.rowid, {new_cols});
+         END;
+         CREATE TRIGGER {fts}_ad AFTER DELETE ON {src} BEGIN
+           INSERT INTO {fts}({fts}, rowid, {cols}) VALUES ('delete', old.rowid, {old_cols});
+         END;
+         CREATE TRIGGER {fts}_au AFTER UPDATE ON {src} BEGIN
+           INSERT INTO {fts}({fts}, rowid, {cols}) VALUES ('delete', old.rowid, {old_cols});
+           INSERT INTO {fts}(rowid, {cols}) VALUES (new.rowid, {new_cols});
+         END;
+         INSERT INTO {fts}({fts}) VALUES ('rebuild');",
+        src = source_table,
+    );
+
+    let tx = conn.transaction().map_err(|err| err.to_string())?;
+    tx.execute_batch(&sql).map_err(|err| err.to_string())?;
+    tx.commit().map_err(|err| err.to_string())
+}
+
+/// Turns free text into an FTS5 query that can't be a syntax error: every
+/// word becomes a quoted phrase (so quotes, `-`, `:`, `AND`/`NEAR` etc. are
+/// literal) and the words are ANDed. A trailing `*` keeps prefix matching.
+fn sanitize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .filter_map(|word| {
+            let prefix = word.ends_with('*');
+            let term: String = word.chars().filter(|&ch| ch != '"' && ch != '*').collect();
+            if term.is_empty() {
+                return None;
+            }
+            Some(if prefix {
+                format!("\"{}\"*", term)
+            } else {
+                format!("\"{}\"", term)
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn search(
+    db_path: &Path,
+    source_table: &str,
+    query: &str,
+    limit: u32,
+) -> Result<Vec<SearchHit>, String> {
+    validate_identifier(source_table)?;
+    let query = sanitize_query(query);
+    if query.is_empty() || limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let conn = super::open_readonly(db_path)?;
+    ensure_fts5(&conn)?;
+    let fts = fts_table(source_table);
+    let sql = format!(
+        "SELECT rowid, snippet({fts}, -1, '[', ']', '…', {SNIPPET_TOKENS}), rank
+         FROM {fts} WHERE {fts} MATCH ?1 ORDER BY rank LIMIT ?2"
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|err| {
+        if err.to_string().contains("no such table") {
+            format!(
+                "No search index for '{}'; call ensure_fts_index first",
+                source_table
+            )
+        } else {
+            err.to_string()
+        }
+    })?;
+    let hits = stmt
+        .query_map(params![query, limit.min(MAX_RESULTS)], |row| {
+            Ok(SearchHit {
+                rowid: row.get(0)?,
+                snippet: row.get(1)?,
+                rank: row.get(2)?,
+            })
+        })
+        .map_err(|err| err.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.to_string())?;
+    Ok(hits)
+}
+
+/// Creates (or rebuilds) the full-text index `<source_table>_fts` over
+/// `columns`, kept in sync with the table by triggers.
+#[tauri::command]
+pub async fn ensure_fts_index(
+    app: AppHandle,
+    db_path: String,
+    source_table: String,
+    columns: Vec<String>,
+) -> Result<(), String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || create_index(&db_path, &source_table, &columns)).await
+}
+
+/// Searches the index built by `ensure_fts_index` for `source_table`, best
+/// matches first (at most 500). `query` is plain user input, not FTS syntax.
+#[tauri::command]
+pub async fn fts_search(
+    app: AppHandle,
+    db_path: String,
+    source_table: String,
+    query: String,
+    limit: u32,
+) -> Result<Vec<SearchHit>, String> {
+    let db_path = super::resolve_db_path(&app, &db_path)?;
+    super::run_blocking(move || search(&db_path, &source_table, &query, limit)).await
+}
@@ -0,0 +1,205 @@
+//! New, empty databases cloned from a configured one, for starting a fresh
+//! matter without carrying over any case data.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+use tauri::AppHandle;
+
+use super::validate_identifier;
+use crate::paths::resolve_app_path;
+
+/// Schema bookkeeping that is always copied, so the template isn't migrated
+/// again from scratch when the app opens it.
+const ALWAYS_KEPT: &[&str] = &["_sqlx_migrations"];
+
+struct SchemaObject {
+    kind: String,
+    name: String,
+    sql: String,
+    is_virtual: bool,
+}
+
+/// Every user-created object with its `CREATE` statement. Internal
+/// `sqlite_*` objects and the shadow tables behind virtual tables are
+/// skipped; SQLite recreates those itself.
+fn schema_objects(conn: &Connection) -> Result<Vec<SchemaObject>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.type, m.name, m.sql, COALESCE(t.type = 'virtual', 0)
+             FROM sqlite_master AS m
+             LEFT JOIN pragma_table_list AS t ON t.schema = 'main' AND t.name = m.name
+             WHERE m.sql IS NOT NULL
+               AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+               AND COALESCE(t.type, '') <> 'shadow'
+             ORDER BY m.rowid",
+        )
+        .map_err(|err| err.to_string())?;
+    let objects = stmt
+        .query_map([], |row| {
+            Ok(SchemaObject {
+                kind: row.get(0)?,
+                name: row.get(1)?,
+                sql: row.get(2)?,
+                is_virtual: row.get(3)?,
+            })
+        })
+        .map_err(|err| err.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|err| err.to_string())?;
+    Ok(objects)
+}
+
+/// Whether `sql` declares an FTS5 table that keeps no content of its own
+/// (`content=''`), which can't be rebuilt.
+fn is_contentless_fts(sql: &str) -> bool {
+    let compact: String = sql
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    compact.contains("content=''") || compact.contains("content=\"\"")
+}
+
+fn create_template(src: &Path, dest: &Path, keep_tables: &[String]) -> Result<(), String> {
+    let source = super::open_readonly(src)?;
+    let objects = schema_objects(&source)?;
+    let user_version: i64 = source
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|err| err.to_string())?;
+    drop(source);
+
+    let tables: HashSet<&str> = objects
+        .iter()
+        .filter(|object| object.kind == "table" && !object.is_virtual)
+        .map(|object| object.name.as_str())
+        .collect();
+    let mut kept: Vec<&str> = Vec::new();
+    for table in keep_tables {
+        validate_identifier(table)?;
+        if !tables.contains(table.as_str()) {
+            return Err(format!("Table '{}' does not exist in the source", table));
+        }
+        if !kept.contains(&table.as_str()) {
+            kept.push(table);
+        }
+    }
+    for table in ALWAYS_KEPT {
+        if tables.contains(table) && !kept.contains(table) {
+            kept.push(table);
+        }
+    }
+
+    let mut conn = Connection::open_with_flags(
+        dest,
+        OpenFlags::SQLITE_OPEN_READ_WRITE
+            | OpenFlags::SQLITE_OPEN_CREATE
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|err| err.to_string())?;
+    conn.execute("ATTACH DATABASE ?1 AS source", [src.to_string_lossy()])
+        .map_err(|err| err.to_string())?;
+
+    let tx = conn.transaction().map_err(|err| err.to_string())?;
+    // Tables first, then data, then indexes, triggers and views, so no
+    // trigger fires while the kept rows are copied.
+    for object in objects.iter().filter(|object| object.kind == "table") {
+        tx.execute_batch(&object.sql)
+            .map_err(|err| format!("Failed to create '{}': {}", object.name, err))?;
+    }
+    for table in &kept {
+        tx.execute_batch(&format!(
+            "INSERT INTO main.\"{0}\" SELECT * FROM source.\"{0}\"",
+            table
+        ))
+        .map_err(|err| format!("Failed to copy '{}': {}", table, err))?;
+    }
+    let has_sequence: bool = tx
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM source.sqlite_master WHERE name = 'sqlite_sequence')
+                AND EXISTS(SELECT 1 FROM main.sqlite_master WHERE name = 'sqlite_sequence')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|err| err.to_string())?;
+    if has_sequence {
+        // AUTOINCREMENT counters carry over only for tables whose rows did.
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO main.sqlite_sequence (name, seq)
+                 SELECT name, seq FROM source.sqlite_sequence WHERE name = ?1",
+            )
+            .map_err(|err| err.to_string())?;
+        for table in &kept {
+            stmt.execute([table]).map_err(|err| err.to_string())?;
+        }
+    }
+    for kind in ["index", "trigger", "view"] {
+        for object in objects.iter().filter(|object| object.kind == kind) {
+            tx.execute_batch(&object.sql)
+                .map_err(|err| format!("Failed to create '{}': {}", object.name, err))?;
+        }
+    }
+    // External-content indexes over kept tables need their entries back.
+    for object in &objects {
+        let is_fts5 = object.is_virtual && object.sql.to_ascii_lowercase().contains("fts5");
+        if is_fts5 && !is_contentless_fts(&object.sql) {
+            tx.execute(
+                &format!(
+                    "INSERT INTO \"{0}\"(\"{0}\") VALUES('rebuild')",
+                    object.name
+                ),
+                [],
+            )
+            .map_err(|err| format!("Failed to rebuild '{}': {}", object.name, err))?;
+        }
+    }
+    tx.pragma_update(None, "user_version", user_version)
+        .map_err(|err| err.to_string())?;
+    tx.commit().map_err(|err| err.to_string())?;
+
+    conn.execute_batch("DETACH DATABASE source")
+        .map_err(|err| err.to_string())
+}
+
+/// Creates `dest_db` with the full schema of `src_db` but data only from
+/// `keep_tables` (e.g. settings, categories); every other table starts empty.
+/// Fails if `dest_db` already exists.
+#[tauri::command]
+pub async fn create_template_from_db(
+    app: AppHandle,
+    src_db: String,
+    dest_db: String,
+    keep_tables: Vec<String>,
+) -> Result<(), String> {
+    let src = super::resolve_db_path(&app, &src_db)?;
+    let dest = resolve_app_path(&app, &dest_db)?;
+    if dest.exists() {
+        return Err(format!("Destination already exists: {}", dest_db));
+    }
+
+    super::run_blocking(move || {
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        // Build under a temporary name so a failure never leaves a
+        // half-made database at `dest`.
+        let tmp = super::sidecar_path(&dest, ".tmp");
+        let _ = fs::remove_file(&tmp);
+        let result = create_template(&src, &tmp, &keep_tables).and_then(|()| {
+            if dest.exists() {
+                return Err(format!("Destination already exists: {}", dest.display()));
+            }
+            fs::rename(&tmp, &dest).map_err(|err| err.to_string())
+        });
+        if result.is_err() {
+            for suffix in [".tmp", ".tmp-journal"] {
+                let _ = fs::remove_file(super::sidecar_path(&dest, suffix));
+            }
+        }
+        result
+    })
+    .await
+}
@@ -0,0 +1,155 @@
+//! Court deadline arithmetic, done in the court's own timezone so DST changes
+//! between the start date and the deadline don't shift the result.
+
+use chrono::{DateTime, Datelike, LocalResult, NaiveDate, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+
+/// Keeps a bogus input from spinning the day-by-day loop for ages.
+const MAX_BUSINESS_DAYS: i32 = 10_000;
+
+fn is_business_day(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Steps `business_days` weekdays from `start` (backwards if negative).
+/// Zero keeps the start date.
+fn add_business_days(start: NaiveDate, business_days: i32) -> Result<NaiveDate, String> {
+    let mut date = start;
+    let mut remaining = business_days.unsigned_abs();
+    while remaining > 0 {
+        date = if business_days > 0 {
+            date.succ_opt()
+        } else {
+            date.pred_opt()
+        }
+        .ok_or_else(|| "Deadline is out of range".to_string())?;
+        if is_business_day(date) {
+            remaining -= 1;
+        }
+    }
+    Ok(date)
+}
+
+/// Last millisecond of `date` in `tz`, as epoch ms.
+fn end_of_day(tz: Tz, date: NaiveDate) -> Result<i64, String> {
+    let last_ms = NaiveTime::from_hms_milli_opt(23, 59, 59, 999).expect("valid time");
+    match tz.from_local_datetime(&date.and_time(last_ms)) {
+        LocalResult::Single(end) | LocalResult::Ambiguous(_, end) => Ok(end.timestamp_millis()),
+        LocalResult::None => Err(format!("{} has no end of day in {}", date, tz)),
+    }
+}
+
+/// Adds `business_days` working days (Monday to Friday) to the local date of
+/// `start_epoch_ms` in the IANA timezone `tz`, e.g. `America/Sao_Paulo`, and
+/// returns the end of that day (23:59:59.999 local) as epoch ms. Holidays
+/// are not skipped.
+#[tauri::command]
+pub fn compute_deadline(
+    start_epoch_ms: i64,
+    business_days: i32,
+    tz: String,
+) -> Result<i64, String> {
+    let tz: Tz = tz
+        .parse()
+        .map_err(|_| format!("Unknown timezone '{}'", tz))?;
+    if business_days.unsigned_abs() > MAX_BUSINESS_DAYS as u32 {
+        return Err(format!(
+            "business_days must be between -{0} and {0}",
+            MAX_BUSINESS_DAYS
+        ));
+    }
+    let start = DateTime::from_timestamp_millis(start_epoch_ms)
+        .ok_or_else(|| format!("Invalid start time {}", start_epoch_ms))?
+        .with_timezone(&tz)
+        .date_naive();
+    end_of_day(tz, add_business_days(start, business_days)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(rfc3339: &str) -> i64 {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn deadline(start: &str, business_days: i32, tz: &str) -> Result<i64, String> {
+        compute_deadline(ms(start), business_days, tz.to_string())
+    }
+
+    #[test]
+    fn counts_weekdays_only() {
+        // 2024-03-04 is a Monday.
+        assert_eq!(
+            deadline("2024-03-04T12:00:00Z", 5, "UTC"),
+            Ok(ms("2024-03-11T23:59:59.999Z"))
+        );
+        assert_eq!(
+            deadline("2024-03-04T12:00:00Z", 10, "UTC"),
+            Ok(ms("2024-03-18T23:59:59.999Z"))
+        );
+    }
+
+    #[test]
+    fn rolls_over_weekends() {
+        // Friday + 1 and Saturday + 1 both land on Monday.
+        assert_eq!(
+            deadline("2024-03-01T12:00:00Z", 1, "UTC"),
+            Ok(ms("2024-03-04T23:59:59.999Z"))
+        );
+        assert_eq!(
+            deadline("2024-03-02T12:00:00Z", 1, "UTC"),
+            Ok(ms("2024-03-04T23:59:59.999Z"))
+        );
+        // Zero keeps the start date, even on a weekend.
+        assert_eq!(
+            deadline("2024-03-02T12:00:00Z", 0, "UTC"),
+            Ok(ms("2024-03-02T23:59:59.999Z"))
+        );
+        // Counting backwards skips the weekend too.
+        assert_eq!(
+            deadline("2024-03-04T12:00:00Z", -1, "UTC"),
+            Ok(ms("2024-03-01T23:59:59.999Z"))
+        );
+    }
+
+    #[test]
+    fn holidays_count_as_business_days() {
+        // Christmas on a Wednesday isn't skipped.
+        assert_eq!(
+            deadline("2024-12-24T12:00:00Z", 1, "UTC"),
+            Ok(ms("2024-12-25T23:59:59.999Z"))
+        );
+    }
+
+    #[test]
+    fn uses_the_local_date_and_end_of_day() {
+        // 02:00 UTC on Friday is still Thursday 23:00 in São Paulo.
+        assert_eq!(
+            deadline("2024-03-01T02:00:00Z", 1, "America/Sao_Paulo"),
+            Ok(ms("2024-03-01T23:59:59.999-03:00"))
+        );
+        // New York switches to daylight time on Sunday 2024-03-10.
+        assert_eq!(
+            deadline("2024-03-08T12:00:00-05:00", 1, "America/New_York"),
+            Ok(ms("2024-03-11T23:59:59.999-04:00"))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(
+            deadline("2024-03-04T12:00:00Z", 1, "Mars/Olympus"),
+            Err("Unknown timezone 'Mars/Olympus'".to_string())
+        );
+        assert_eq!(
+            deadline("2024-03-04T12:00:00Z", MAX_BUSINESS_DAYS + 1, "UTC"),
+            Err(format!(
+                "business_days must be between -{0} and {0}",
+                MAX_BUSINESS_DAYS
+            ))
+        );
+    }
+}
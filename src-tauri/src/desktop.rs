@@ -0,0 +1,212 @@
+//! Integration with the desktop environment (file manager, default handlers,
+//! system theme).
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State, Theme, WindowEvent};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::paths::{resolve_app_path, ApprovedSavePaths};
+use crate::window_state::main_window;
+
+/// Emitted in the running instance when the app is launched again, carrying
+/// the second launch's arguments (e.g. a file the user double-clicked).
+const SECOND_INSTANCE_EVENT: &str = "second-instance";
+
+#[derive(Clone, Serialize)]
+struct SecondInstance {
+    args: Vec<String>,
+    cwd: String,
+}
+
+/// Called by the single-instance plugin in the already-running process; the
+/// new process exits right after, so only one ever opens the database.
+pub fn on_second_instance(app: &AppHandle, args: Vec<String>, cwd: String) {
+    match main_window(app) {
+        Ok(window) => {
+            let _ = window.unminimize();
+            let _ = window.show();
+            if let Err(err) = window.set_focus() {
+                log::warn!("Failed to focus main window: {}", err);
+            }
+        }
+        Err(err) => log::warn!("Cannot bring app to front: {}", err),
+    }
+    // The first argument is the executable path.
+    let args = args.into_iter().skip(1).collect();
+    if let Err(err) = app.emit(SECOND_INSTANCE_EVENT, SecondInstance { args, cwd }) {
+        log::warn!("Failed to emit {}: {}", SECOND_INSTANCE_EVENT, err);
+    }
+}
+
+/// Opens the system file manager with `path` selected (Explorer `/select`,
+/// Finder reveal, freedesktop `FileManager1.ShowItems`). Where selection isn't
+/// available the containing directory is opened instead. A relative `path` is
+/// taken as relative to the app data dir, never the process's working dir.
+#[tauri::command]
+pub fn reveal_in_file_manager(app: AppHandle, path: String) -> Result<(), String> {
+    let path = if Path::new(&path).is_absolute() {
+        PathBuf::from(&path)
+    } else {
+        resolve_app_path(&app, &path)?
+    };
+    let path = path.as_path();
+    if !path.exists() {
+        return Err(format!("Path does not exist: {}", path.display()));
+    }
+
+    match tauri_plugin_opener::reveal_item_in_dir(path) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            log::warn!(
+                "Reveal not supported ({}), opening containing directory",
+                err
+            );
+            let dir = if path.is_dir() {
+                path
+            } else {
+                path.parent().unwrap_or(path)
+            };
+            tauri_plugin_opener::open_path(dir, None::<&str>).map_err(|err| err.to_string())
+        }
+    }
+}
+
+const ALLOWED_URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Opens a web or mail link in the default handler. Anything else (`file:`,
+/// `javascript:`, custom protocol handlers) is refused so links inside
+/// documents can't launch local programs. Every opened URL is logged.
+#[tauri::command]
+pub fn open_external_url(url: String) -> Result<(), String> {
+    let parsed =
+        tauri::Url::parse(url.trim()).map_err(|err| format!("Invalid URL '{}': {}", url, err))?;
+    if !ALLOWED_URL_SCHEMES.contains(&parsed.scheme()) {
+        return Err(format!(
+            "URL scheme '{}' is not allowed; only {} links can be opened",
+            parsed.scheme(),
+            ALLOWED_URL_SCHEMES.join(", ")
+        ));
+    }
+    log::info!("Opening external URL: {}", parsed);
+    tauri_plugin_opener::open_url(parsed.as_str(), None::<&str>).map_err(|err| err.to_string())
+}
+
+/// Shows the native save dialog with a suggested file name and extension
+/// filters, given as `[name, [extensions]]` pairs such as
+/// `["Backup", ["jdbak"]]`. Returns `None` when the user cancels.
+///
+/// The chosen absolute path can be passed once to an export command
+/// (`backup_database`, `export_query_csv`, `export_logs`, ...), which would
+/// otherwise only write under the app data dir.
+#[tauri::command]
+pub async fn prompt_save_path(
+    app: AppHandle,
+    approved: State<'_, ApprovedSavePaths>,
+    default_name: String,
+    filters: Vec<(String, Vec<String>)>,
+) -> Result<Option<String>, String> {
+    let mut dialog = app.dialog().file().set_file_name(default_name);
+    for (name, extensions) in &filters {
+        // Accept "pdf", ".pdf" and "*.pdf" alike.
+        let extensions: Vec<&str> = extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('*').trim_start_matches('.'))
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        if extensions.is_empty() {
+            return Err(format!("Filter '{}' has no extensions", name));
+        }
+        dialog = dialog.add_filter(name, &extensions);
+    }
+    if let Ok(window) = main_window(&app) {
+        dialog = dialog.set_parent(&window);
+    }
+
+    // The blocking variant must not run on the main thread.
+    let chosen = crate::db::run_blocking(move || Ok(dialog.blocking_save_file())).await?;
+    let Some(chosen) = chosen else {
+        return Ok(None);
+    };
+    let path = chosen.into_path().map_err(|err| err.to_string())?;
+    let shown = path.display().to_string();
+    approved.approve(PathBuf::from(&shown));
+    Ok(Some(shown))
+}
+
+/// Shows the native folder dialog. Returns `None` when the user cancels.
+///
+/// Like a `prompt_save_path` result, the chosen folder is approved once as a
+/// destination, here for `set_backup_schedule`.
+#[tauri::command]
+pub async fn prompt_folder_path(
+    app: AppHandle,
+    approved: State<'_, ApprovedSavePaths>,
+) -> Result<Option<String>, String> {
+    let mut dialog = app.dialog().file();
+    if let Ok(window) = main_window(&app) {
+        dialog = dialog.set_parent(&window);
+    }
+
+    // The blocking variant must not run on the main thread.
+    let chosen = crate::db::run_blocking(move || Ok(dialog.blocking_pick_folder())).await?;
+    let Some(chosen) = chosen else {
+        return Ok(None);
+    };
+    let path = chosen.into_path().map_err(|err| err.to_string())?;
+    let shown = path.display().to_string();
+    approved.approve(PathBuf::from(&shown));
+    Ok(Some(shown))
+}
+
+const SYSTEM_THEME_CHANGED_EVENT: &str = "system-theme-changed";
+
+#[derive(Clone, Serialize)]
+struct SystemThemeChanged {
+    theme: &'static str,
+}
+
+fn theme_name(theme: Theme) -> &'static str {
+    match theme {
+        Theme::Dark => "dark",
+        _ => "light",
+    }
+}
+
+/// `"light"` or `"dark"`, as the OS reports it for the main window. Platforms
+/// that don't report a theme (some Linux desktops) yield `"light"`; without a
+/// main window this is an error.
+#[tauri::command]
+pub fn get_system_theme(app: AppHandle) -> Result<String, String> {
+    match main_window(&app)?.theme() {
+        Ok(theme) => Ok(theme_name(theme).to_string()),
+        Err(err) => {
+            log::debug!("System theme unavailable, assuming light: {}", err);
+            Ok(theme_name(Theme::Light).to_string())
+        }
+    }
+}
+
+/// Forwards the main window's theme changes as `system-theme-changed`
+/// events. Call once from `setup`.
+pub fn watch_system_theme(app: &AppHandle) {
+    let window = match main_window(app) {
+        Ok(window) => window,
+        Err(err) => {
+            log::warn!("Not watching the system theme: {}", err);
+            return;
+        }
+    };
+    let app = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::ThemeChanged(theme) = event {
+            let payload = SystemThemeChanged {
+                theme: theme_name(*theme),
+            };
+            if let Err(err) = app.emit(SYSTEM_THEME_CHANGED_EVENT, payload) {
+                log::warn!("Failed to emit {}: {}", SYSTEM_THEME_CHANGED_EVENT, err);
+            }
+        }
+    });
+}
@@ -0,0 +1,174 @@
+//! Information about the running app for the About and diagnostics screens.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::db::maintenance::{self, EncryptionStatus, IntegrityReport};
+use crate::secrets::{self, BackendInfo};
+use crate::{db, logging, notifications};
+
+#[derive(Serialize)]
+pub struct AppInfo {
+    pub version: String,
+    pub tauri_version: String,
+    pub config_dir: String,
+    pub data_dir: String,
+    pub log_dir: String,
+}
+
+#[tauri::command]
+pub fn get_app_info(app: AppHandle) -> Result<AppInfo, String> {
+    let path = app.path();
+    let dir = |resolved: tauri::Result<std::path::PathBuf>| {
+        resolved
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .map_err(|err| err.to_string())
+    };
+    Ok(AppInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        config_dir: dir(path.app_config_dir())?,
+        data_dir: crate::paths::data_dir(&app)?.to_string_lossy().into_owned(),
+        log_dir: dir(path.app_log_dir())?,
+    })
+}
+
+/// Logs one `key=value` line describing the environment the app started in,
+/// so a support log shows it without asking the user.
+pub fn log_startup(app: &AppHandle, devtools_opened: bool) {
+    let data_dir = match crate::paths::data_dir(app) {
+        Ok(dir) => dir.display().to_string(),
+        Err(err) => format!("<unresolved: {}>", err),
+    };
+    log::info!(
+        "startup version={} tauri={} os={} arch={} devtools={} secret_backend={} data_dir={:?}",
+        env!("CARGO_PKG_VERSION"),
+        tauri::VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        devtools_opened,
+        secrets::backend_name(),
+        data_dir
+    );
+}
+
+/// Outcome of one diagnostic, serialized as `{"status": "ok", "value": ...}`
+/// or `{"status": "failed", "value": "<error>"}`.
+#[derive(Serialize)]
+#[serde(tag = "status", content = "value", rename_all = "snake_case")]
+pub enum Check<T> {
+    Ok(T),
+    Failed(String),
+}
+
+impl<T> From<Result<T, String>> for Check<T> {
+    fn from(result: Result<T, String>) -> Self {
+        match result {
+            Ok(value) => Check::Ok(value),
+            Err(err) => Check::Failed(err),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DiskSpace {
+    pub path: String,
+    pub available_bytes: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct DirSize {
+    pub path: String,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+#[derive(Serialize)]
+pub struct Diagnostics {
+    pub database_integrity: Check<IntegrityReport>,
+    pub database_encryption: Check<EncryptionStatus>,
+    pub secret_backend: Check<BackendInfo>,
+    pub data_dir_disk_space: Check<DiskSpace>,
+    pub log_dir_size: Check<DirSize>,
+    /// `"granted"`, `"denied"` or `"default"`.
+    pub notification_permission: Check<String>,
+}
+
+fn disk_space(app: &AppHandle) -> Result<DiskSpace, String> {
+    let dir = crate::paths::data_dir(app)?;
+    Ok(DiskSpace {
+        available_bytes: fs4::available_space(&dir).map_err(|err| err.to_string())?,
+        total_bytes: fs4::total_space(&dir).map_err(|err| err.to_string())?,
+        path: dir.display().to_string(),
+    })
+}
+
+/// Total size and count of the files under `dir`, recursively.
+fn dir_size(dir: &Path) -> std::io::Result<(u64, u64)> {
+    let mut bytes = 0;
+    let mut files = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let (dir_bytes, dir_files) = dir_size(&entry.path())?;
+            bytes += dir_bytes;
+            files += dir_files;
+        } else if file_type.is_file() {
+            bytes += entry.metadata()?.len();
+            files += 1;
+        }
+    }
+    Ok((bytes, files))
+}
+
+fn log_dir_size(app: &AppHandle) -> Result<DirSize, String> {
+    let dir = logging::log_dir(app)?;
+    let (bytes, files) = dir_size(&dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    Ok(DirSize {
+        path: dir.display().to_string(),
+        bytes,
+        files,
+    })
+}
+
+/// Runs every subsystem check for a support "run diagnostics" screen. Each
+/// check reports its own result, so one failure doesn't hide the others.
+#[tauri::command]
+pub async fn run_diagnostics(app: AppHandle, db_path: String) -> Result<Diagnostics, String> {
+    let db_path = db::resolve_db_path(&app, &db_path);
+    let blocking_app = app.clone();
+    // The file-system checks can take a while on a large or slow disk.
+    let (database_integrity, database_encryption, data_dir_disk_space, log_dir_size) =
+        db::run_blocking(move || {
+            let (integrity, encryption) = match &db_path {
+                Ok(path) => (
+                    maintenance::check_integrity(path),
+                    maintenance::encryption_status(path),
+                ),
+                Err(err) => (Err(err.clone()), Err(err.clone())),
+            };
+            Ok((
+                integrity.into(),
+                encryption.into(),
+                disk_space(&blocking_app).into(),
+                log_dir_size(&blocking_app).into(),
+            ))
+        })
+        .await?;
+
+    Ok(Diagnostics {
+        database_integrity,
+        database_encryption,
+        secret_backend: secrets::secret_backend_info()
+            .map_err(|err| err.to_string())
+            .into(),
+        data_dir_disk_space,
+        log_dir_size,
+        notification_permission: notifications::notification_permission_state(app).into(),
+    })
+}
@@ -0,0 +1,95 @@
+//! Warns before the data directory's volume fills up, since SQLite and file
+//! writes only fail with unhelpful I/O errors once it does.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::notifications;
+use crate::paths;
+use crate::settings;
+
+const LOW_DISK_SPACE_EVENT: &str = "low-disk-space";
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Used until the user picks a threshold.
+const DEFAULT_THRESHOLD_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Set once a warning has gone out, and cleared when free space is back
+/// above the threshold, so each drop below it warns only once.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Serialize)]
+struct LowDiskSpace {
+    path: String,
+    available_bytes: u64,
+    threshold_bytes: u64,
+}
+
+fn check(app: &AppHandle) -> Result<(), String> {
+    let threshold = settings::load(app)
+        .low_disk_threshold_bytes
+        .unwrap_or(DEFAULT_THRESHOLD_BYTES);
+    if threshold == 0 {
+        return Ok(());
+    }
+    let dir = paths::data_dir(app)?;
+    let available = fs4::available_space(&dir).map_err(|err| err.to_string())?;
+    if available >= threshold {
+        WARNED.store(false, Ordering::SeqCst);
+        return Ok(());
+    }
+    if WARNED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    log::warn!(
+        "Low disk space in {}: {} bytes free, threshold {}",
+        dir.display(),
+        available,
+        threshold
+    );
+    let payload = LowDiskSpace {
+        path: dir.display().to_string(),
+        available_bytes: available,
+        threshold_bytes: threshold,
+    };
+    if let Err(err) = app.emit(LOW_DISK_SPACE_EVENT, payload) {
+        log::warn!("Failed to emit {}: {}", LOW_DISK_SPACE_EVENT, err);
+    }
+    let body = format!(
+        "Restam {} MB livres no disco dos dados do JurisDesk. Libere espaço para evitar falhas ao salvar.",
+        available / (1024 * 1024)
+    );
+    if let Err(err) = notifications::show(app, "Pouco espaço em disco", &body) {
+        log::warn!("Failed to show low disk space notification: {}", err);
+    }
+    Ok(())
+}
+
+/// Starts the periodic check, the first one right away; call once from
+/// `setup`, after `paths::init`.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(err) = check(&app) {
+                log::warn!("Disk space check failed: {}", err);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// Sets and saves the free-space level below which the user is warned; 0
+/// turns the warning off. Takes effect at the next check.
+#[tauri::command]
+pub fn set_low_disk_threshold(app: AppHandle, bytes: u64) -> Result<(), String> {
+    settings::update(&app, |settings| {
+        settings.low_disk_threshold_bytes = Some(bytes)
+    })?;
+    // Judge the new threshold afresh rather than by the old one's warning.
+    WARNED.store(false, Ordering::SeqCst);
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Error type for commands whose failures the frontend needs to tell apart.
+//!
+//! Serializes as `{ "code": "not_found", "message": "..." }`, so callers can
+//! branch on `code` and show `message`.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::crypto::OpenError;
+
+#[derive(Debug)]
+pub enum CommandError {
+    NotFound(String),
+    InvalidInput(String),
+    /// The OS refused access, e.g. a locked keychain or a denied prompt.
+    Permission(String),
+    WrongPassphrase,
+    Corrupt(String),
+    Io(String),
+    /// A rate limit was hit; the message says when to retry.
+    TooManyRequests(String),
+    /// The underlying store or service failed for another reason.
+    Backend(String),
+}
+
+impl CommandError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommandError::NotFound(_) => "not_found",
+            CommandError::InvalidInput(_) => "invalid_input",
+            CommandError::Permission(_) => "permission",
+            CommandError::WrongPassphrase => "wrong_passphrase",
+            CommandError::Corrupt(_) => "corrupt",
+            CommandError::Io(_) => "io",
+            CommandError::TooManyRequests(_) => "too_many_requests",
+            CommandError::Backend(_) => "backend",
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::WrongPassphrase => write!(f, "{}", OpenError::WrongPassphrase),
+            CommandError::Corrupt(reason) => write!(f, "File is corrupt: {}", reason),
+            CommandError::NotFound(message)
+            | CommandError::InvalidInput(message)
+            | CommandError::Permission(message)
+            | CommandError::Io(message)
+            | CommandError::TooManyRequests(message)
+            | CommandError::Backend(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CommandError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// Lets `?` pass a `CommandError` through code that still returns strings.
+impl From<CommandError> for String {
+    fn from(err: CommandError) -> Self {
+        err.to_string()
+    }
+}
+
+impl From<keyring::Error> for CommandError {
+    fn from(err: keyring::Error) -> Self {
+        let message = err.to_string();
+        match err {
+            keyring::Error::NoEntry => CommandError::NotFound(message),
+            keyring::Error::NoStorageAccess(_) => CommandError::Permission(message),
+            keyring::Error::BadEncoding(_)
+            | keyring::Error::TooLong(_, _)
+            | keyring::Error::Invalid(_, _) => CommandError::InvalidInput(message),
+            _ => CommandError::Backend(message),
+        }
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => CommandError::NotFound(err.to_string()),
+            std::io::ErrorKind::PermissionDenied => CommandError::Permission(err.to_string()),
+            _ => CommandError::Io(err.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(err: serde_json::Error) -> Self {
+        CommandError::Corrupt(err.to_string())
+    }
+}
+
+impl From<OpenError> for CommandError {
+    fn from(err: OpenError) -> Self {
+        match err {
+            OpenError::WrongPassphrase => CommandError::WrongPassphrase,
+            OpenError::Corrupt(reason) => CommandError::Corrupt(reason),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Operations on user documents stored under the app data directory.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::db::run_blocking;
+use crate::paths::resolve_app_path;
+
+const MIN_WIPE_PASSES: u8 = 1;
+const MAX_WIPE_PASSES: u8 = 7;
+const WIPE_CHUNK: usize = 64 * 1024;
+
+fn wipe(path: &Path, passes: u8) -> Result<(), String> {
+    let meta = fs::symlink_metadata(path).map_err(|err| err.to_string())?;
+    if !meta.is_file() {
+        return Err(format!("Not a regular file: {}", path.display()));
+    }
+    let len = meta.len();
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|err| err.to_string())?;
+    let mut chunk = vec![0u8; WIPE_CHUNK];
+    let mut rng = rand::thread_rng();
+    for _ in 0..passes {
+        file.seek(SeekFrom::Start(0))
+            .map_err(|err| err.to_string())?;
+        let mut remaining = len;
+        while remaining > 0 {
+            let size = remaining.min(WIPE_CHUNK as u64) as usize;
+            rng.fill_bytes(&mut chunk[..size]);
+            file.write_all(&chunk[..size])
+                .map_err(|err| err.to_string())?;
+            remaining -= size as u64;
+        }
+        file.sync_all().map_err(|err| err.to_string())?;
+    }
+    drop(file);
+    fs::remove_file(path).map_err(|err| err.to_string())
+}
+
+/// Overwrites a file with random bytes `passes` times (clamped to 1..=7),
+/// syncing after each pass, then deletes it.
+///
+/// Best-effort only: SSD wear-leveling and copy-on-write filesystems may keep
+/// old blocks elsewhere on the device. Success means the file is gone and its
+/// blocks were overwritten as far as the OS lets us see.
+#[tauri::command]
+pub async fn secure_delete_file(app: AppHandle, path: String, passes: u8) -> Result<(), String> {
+    let path = resolve_app_path(&app, &path)?;
+    let passes = passes.clamp(MIN_WIPE_PASSES, MAX_WIPE_PASSES);
+    run_blocking(move || wipe(&path, passes)).await
+}
+
+/// Larger files are refused outright; a PDF is parsed fully in memory.
+const MAX_PDF_BYTES: u64 = 100 * 1024 * 1024;
+/// Pages past this are not extracted.
+const MAX_PDF_PAGES: usize = 2000;
+const PAGE_BREAK: char = '\u{000C}';
+
+fn pdf_text(path: &Path) -> Result<String, String> {
+    let size = fs::metadata(path).map_err(|err| err.to_string())?.len();
+    if size > MAX_PDF_BYTES {
+        return Err(format!(
+            "PDF is too large to index ({} MiB; the limit is {} MiB)",
+            size / (1024 * 1024),
+            MAX_PDF_BYTES / (1024 * 1024)
+        ));
+    }
+    let data = fs::read(path).map_err(|err| err.to_string())?;
+    let doc = lopdf::Document::load_mem(&data)
+        .map_err(|err| format!("Failed to read PDF {}: {}", path.display(), err))?;
+    if doc.is_encrypted() {
+        return Err("PDF is password-protected".to_string());
+    }
+
+    let pages: Vec<u32> = doc.get_pages().into_keys().collect();
+    if pages.len() > MAX_PDF_PAGES {
+        log::warn!(
+            "{} has {} pages; extracting only the first {}",
+            path.display(),
+            pages.len(),
+            MAX_PDF_PAGES
+        );
+    }
+    let texts: Vec<String> = pages
+        .iter()
+        .take(MAX_PDF_PAGES)
+        // A page whose content can't be decoded counts as having no text.
+        .map(|page| doc.extract_text(&[*page]).unwrap_or_default())
+        .collect();
+    if texts.iter().all(|text| text.trim().is_empty()) {
+        return Err(
+            "PDF has no text layer (likely a scanned image); run OCR to make it searchable"
+                .to_string(),
+        );
+    }
+    Ok(texts.join(&PAGE_BREAK.to_string()))
+}
+
+/// Returns the text of a PDF for the search index, with pages separated by
+/// form feeds (`\f`). Image-only scans are an error so the UI can offer OCR.
+#[tauri::command]
+pub async fn extract_pdf_text(app: AppHandle, path: String) -> Result<String, String> {
+    let path = resolve_app_path(&app, &path)?;
+    run_blocking(move || pdf_text(&path)).await
+}
+
+const HASH_CHUNK: usize = 64 * 1024;
+
+enum Checksum {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Checksum {
+    fn new(algorithm: &str) -> Result<Self, String> {
+        match algorithm.to_ascii_lowercase().as_str() {
+            "sha256" => Ok(Checksum::Sha256(Sha256::new())),
+            "blake3" => Ok(Checksum::Blake3(Box::default())),
+            _ => Err(format!(
+                "Unknown checksum algorithm '{}'; expected sha256 or blake3",
+                algorithm
+            )),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Checksum::Sha256(hasher) => hasher.update(data),
+            Checksum::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Checksum::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Checksum::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Hashes a file in fixed-size chunks, so memory use doesn't grow with it.
+pub(crate) fn checksum(path: &Path, algorithm: &str) -> Result<String, String> {
+    let mut hasher = Checksum::new(algorithm)?;
+    let mut file = fs::File::open(path).map_err(|err| err.to_string())?;
+    let mut chunk = vec![0u8; HASH_CHUNK];
+    loop {
+        let read = match file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.to_string()),
+        };
+        hasher.update(&chunk[..read]);
+    }
+    Ok(hasher.finish())
+}
+
+/// Lowercase hex digest of a file; `algorithm` is `"sha256"` or `"blake3"`
+/// (any case).
+#[tauri::command]
+pub async fn file_checksum(
+    app: AppHandle,
+    path: String,
+    algorithm: String,
+) -> Result<String, String> {
+    let path = resolve_app_path(&app, &path)?;
+    run_blocking(move || checksum(&path, &algorithm)).await
+}
+
+/// Whether a file still matches a digest recorded earlier by `file_checksum`.
+/// `expected` is compared case-insensitively.
+#[tauri::command]
+pub async fn verify_checksum(
+    app: AppHandle,
+    path: String,
+    algorithm: String,
+    expected: String,
+) -> Result<bool, String> {
+    let path = resolve_app_path(&app, &path)?;
+    let actual = run_blocking(move || checksum(&path, &algorithm)).await?;
+    Ok(actual.eq_ignore_ascii_case(expected.trim()))
+}
+
+/// Directories nested deeper than this below the scanned one are not entered.
+const MAX_SCAN_DEPTH: usize = 32;
+
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    /// Size of each copy in bytes.
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct FileError {
+    pub path: String,
+    pub message: String,
+}
+
+impl FileError {
+    fn new(path: &Path, err: impl ToString) -> Self {
+        Self {
+            path: path.display().to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DuplicateReport {
+    pub groups: Vec<DuplicateGroup>,
+    /// Files and directories that couldn't be read; the scan carried on.
+    pub errors: Vec<FileError>,
+}
+
+/// Regular files under `dir` keyed by size. Symlinks aren't followed, so a
+/// link loop can't make the walk run forever.
+fn files_by_size(
+    dir: &Path,
+    relative: &Path,
+    depth: usize,
+    sizes: &mut HashMap<u64, Vec<PathBuf>>,
+    errors: &mut Vec<FileError>,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            errors.push(FileError::new(relative, err));
+            return;
+        }
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                errors.push(FileError::new(relative, err));
+                continue;
+            }
+        };
+        let path = relative.join(entry.file_name());
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(err) => {
+                errors.push(FileError::new(&path, err));
+                continue;
+            }
+        };
+        if file_type.is_dir() {
+            if depth < MAX_SCAN_DEPTH {
+                files_by_size(&entry.path(), &path, depth + 1, sizes, errors);
+            } else {
+                log::warn!("Not scanning {}: nested too deeply", path.display());
+            }
+        } else if file_type.is_file() {
+            match entry.metadata() {
+                Ok(meta) => sizes.entry(meta.len()).or_default().push(path),
+                Err(err) => errors.push(FileError::new(&path, err)),
+            }
+        }
+    }
+}
+
+fn find_duplicates(root: &Path, display_root: &Path) -> DuplicateReport {
+    let mut sizes = HashMap::new();
+    let mut errors = Vec::new();
+    files_by_size(root, display_root, 0, &mut sizes, &mut errors);
+
+    let mut groups = Vec::new();
+    // Only files that share a size can share content; empty files are all
+    // alike and not worth reporting.
+    for (size, paths) in sizes {
+        if size == 0 || paths.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for path in paths {
+            let full = root.join(path.strip_prefix(display_root).unwrap_or(&path));
+            match checksum(&full, "blake3") {
+                Ok(hash) => by_hash
+                    .entry(hash)
+                    .or_default()
+                    .push(path.display().to_string()),
+                Err(message) => errors.push(FileError::new(&path, message)),
+            }
+        }
+        groups.extend(
+            by_hash
+                .into_iter()
+                .filter(|(_, paths)| paths.len() > 1)
+                .map(|(hash, mut paths)| {
+                    paths.sort();
+                    DuplicateGroup { hash, size, paths }
+                }),
+        );
+    }
+    // Most wasted space first.
+    groups.sort_by(|a, b| {
+        let wasted = |group: &DuplicateGroup| group.size * (group.paths.len() as u64 - 1);
+        wasted(b)
+            .cmp(&wasted(a))
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    DuplicateReport { groups, errors }
+}
+
+/// Groups the files under `dir` (recursively) that have identical content,
+/// such as the same exhibit uploaded twice under different names. Paths are
+/// returned as `dir` joined with the path inside it, ready to pass back to
+/// other commands. Unreadable files are listed in `errors` instead of failing
+/// the scan.
+#[tauri::command]
+pub async fn find_duplicate_files(app: AppHandle, dir: String) -> Result<DuplicateReport, String> {
+    let root = resolve_app_path(&app, &dir)?;
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", dir));
+    }
+    let display_root = PathBuf::from(dir.trim());
+    run_blocking(move || Ok(find_duplicates(&root, &display_root))).await
+}
@@ -0,0 +1,119 @@
+//! Locks the app after a period without user input, for workstations shared
+//! between several people. The frontend reports input through
+//! `register_activity` and shows its lock screen on `app-locked`.
+
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::secrets::{self, device::DeviceKey};
+use crate::settings;
+
+const APP_LOCKED_EVENT: &str = "app-locked";
+/// How often the background task compares the idle time to the timeout.
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Serialize)]
+struct AppLocked {
+    idle_minutes: u32,
+}
+
+struct IdleState {
+    last_activity: Instant,
+    /// 0 disables auto-lock.
+    timeout_minutes: u32,
+    locked: bool,
+}
+
+pub struct IdleLock(Mutex<IdleState>);
+
+impl Default for IdleLock {
+    fn default() -> Self {
+        Self(Mutex::new(IdleState {
+            last_activity: Instant::now(),
+            timeout_minutes: 0,
+            locked: false,
+        }))
+    }
+}
+
+impl IdleLock {
+    fn state(&self) -> MutexGuard<'_, IdleState> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Locks if the timeout has passed. Returns the timeout that was exceeded.
+fn check(idle: &IdleLock) -> Option<u32> {
+    let mut state = idle.state();
+    if state.locked || state.timeout_minutes == 0 {
+        return None;
+    }
+    let timeout = Duration::from_secs(u64::from(state.timeout_minutes) * 60);
+    if state.last_activity.elapsed() < timeout {
+        return None;
+    }
+    state.locked = true;
+    Some(state.timeout_minutes)
+}
+
+fn lock(app: &AppHandle, idle_minutes: u32) {
+    secrets::forget_cached();
+    app.state::<DeviceKey>().forget();
+    log::info!("Locked after {} idle minute(s)", idle_minutes);
+    if let Err(err) = app.emit(APP_LOCKED_EVENT, AppLocked { idle_minutes }) {
+        log::warn!("Failed to emit {}: {}", APP_LOCKED_EVENT, err);
+    }
+}
+
+/// Loads the saved timeout and starts the idle check. Call once from `setup`,
+/// after `IdleLock` is managed.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    app.state::<IdleLock>().state().timeout_minutes = settings::load(&app).idle_timeout_minutes;
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            if let Some(idle_minutes) = check(&app.state::<IdleLock>()) {
+                lock(&app, idle_minutes);
+            }
+        }
+    });
+}
+
+/// Resets the idle timer. Called by the frontend on user input (throttled on
+/// its side); ignored while locked, so only `unlock` ends a lock.
+#[tauri::command]
+pub fn register_activity(idle: State<'_, IdleLock>) {
+    let mut state = idle.state();
+    if !state.locked {
+        state.last_activity = Instant::now();
+    }
+}
+
+/// Sets and saves the auto-lock timeout; 0 disables auto-lock.
+#[tauri::command]
+pub fn set_idle_timeout(
+    app: AppHandle,
+    idle: State<'_, IdleLock>,
+    minutes: u32,
+) -> Result<(), String> {
+    settings::update(&app, |settings| settings.idle_timeout_minutes = minutes)?;
+    let mut state = idle.state();
+    state.timeout_minutes = minutes;
+    state.last_activity = Instant::now();
+    Ok(())
+}
+
+/// Ends a lock and restarts the idle timer. The frontend gates this behind
+/// its own lock screen; secrets are loaded again on their next use.
+#[tauri::command]
+pub fn unlock(idle: State<'_, IdleLock>) {
+    let mut state = idle.state();
+    state.locked = false;
+    state.last_activity = Instant::now();
+}
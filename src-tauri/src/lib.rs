@@ -1,67 +1,186 @@
 use tauri::Manager;
 
-#[tauri::command]
-fn set_secret(service: String, key: String, value: String) -> Result<(), String> {
-    let entry = keyring::Entry::new(&service, &key).map_err(|err| err.to_string())?;
-    entry.set_password(&value).map_err(|err| err.to_string())
-}
-
-#[tauri::command]
-fn get_secret(service: String, key: String) -> Result<Option<String>, String> {
-    let entry = keyring::Entry::new(&service, &key).map_err(|err| err.to_string())?;
-    match entry.get_password() {
-        Ok(secret) => Ok(Some(secret)),
-        Err(keyring::Error::NoEntry) => Ok(None),
-        Err(err) => Err(err.to_string()),
-    }
-}
-
-#[tauri::command]
-fn delete_secret(service: String, key: String) -> Result<(), String> {
-    let entry = keyring::Entry::new(&service, &key).map_err(|err| err.to_string())?;
-    match entry.delete_credential() {
-        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
-        Err(err) => Err(err.to_string()),
-    }
-}
+mod audit;
+mod crash;
+mod credentials;
+mod crypto;
+mod db;
+mod deadlines;
+mod desktop;
+mod diagnostics;
+mod disk_monitor;
+mod error;
+mod files;
+mod idle;
+mod logging;
+mod notifications;
+mod paths;
+mod progress;
+mod ratelimit;
+mod relocate;
+mod secrets;
+mod settings;
+mod watch;
+mod window_state;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Set up panic hook for logging before abort
+    // Set up panic hook for logging and a crash report before abort
     std::panic::set_hook(Box::new(|info| {
         log::error!("Panic occurred: {}", info);
-        // Note: SQLite in WAL mode handles checkpoints automatically,
-        // so no explicit cleanup is needed here
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str));
+        crash::write_report(&info.to_string(), payload);
     }));
 
-    if let Err(e) = tauri::Builder::default()
+    let app = tauri::Builder::default()
+        // Must be registered first. The lock is an OS object (named mutex,
+        // D-Bus name, socket) owned by the process, so a crash releases it.
+        .plugin(tauri_plugin_single_instance::init(
+            desktop::on_second_instance,
+        ))
         .plugin(tauri_plugin_sql::Builder::new().build())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .manage(watch::Watchers::default())
+        .manage(ratelimit::RateLimiter::default())
+        .manage(idle::IdleLock::default())
+        .manage(secrets::device::DeviceKey::default())
+        .manage(paths::ApprovedSavePaths::default())
+        .on_window_event(window_state::on_window_event)
         .invoke_handler(tauri::generate_handler![
-            set_secret,
-            get_secret,
-            delete_secret
+            secrets::set_secret,
+            secrets::set_secrets,
+            secrets::get_secret,
+            secrets::set_secret_bytes,
+            secrets::get_secret_bytes,
+            secrets::delete_secret,
+            secrets::has_secret,
+            secrets::list_secrets,
+            secrets::rotate_secret,
+            secrets::migrate_secrets_to_profile,
+            secrets::clear_all_secrets,
+            secrets::import_secrets,
+            secrets::export_secrets,
+            secrets::copy_secret_to_clipboard,
+            secrets::get_secret_metadata,
+            secrets::secret_backend_info,
+            secrets::compact_secret_store,
+            secrets::device::ensure_device_key,
+            db::backup::backup_database,
+            db::backup::restore_database,
+            db::backup::verify_backup,
+            db::backup::incremental_backup,
+            db::auto_backup::set_backup_schedule,
+            db::auto_backup::get_backup_schedule,
+            db::maintenance::checkpoint_wal,
+            db::maintenance::recover_stale_lock,
+            db::maintenance::check_database_integrity,
+            db::maintenance::vacuum_database,
+            db::maintenance::database_stats,
+            db::maintenance::check_migrations,
+            db::maintenance::database_encryption_status,
+            db::query::export_query_csv,
+            db::import::import_csv,
+            db::query::run_readonly_query,
+            db::query::query_page,
+            db::query::run_safe_query,
+            db::query::execute_transaction,
+            db::query::execute_returning_id,
+            db::query::run_query_with_attach,
+            db::search::ensure_fts_index,
+            db::search::fts_search,
+            db::template::create_template_from_db,
+            db::archive::export_case_archive,
+            db::archive::import_case_archive,
+            logging::set_log_level,
+            logging::rotate_logs,
+            logging::tail_log,
+            logging::export_logs,
+            desktop::reveal_in_file_manager,
+            desktop::open_external_url,
+            desktop::prompt_save_path,
+            desktop::prompt_folder_path,
+            desktop::get_system_theme,
+            notifications::schedule_notification,
+            notifications::cancel_scheduled_notification,
+            notifications::notification_permission_state,
+            notifications::request_notification_permission,
+            deadlines::compute_deadline,
+            audit::audit_log,
+            audit::verify_audit_chain,
+            files::secure_delete_file,
+            files::extract_pdf_text,
+            files::file_checksum,
+            files::verify_checksum,
+            files::find_duplicate_files,
+            watch::watch_directory,
+            watch::unwatch_directory,
+            window_state::reset_window_state,
+            settings::export_settings_snapshot,
+            settings::diff_settings_snapshots,
+            relocate::relocate_data_directory,
+            relocate::get_data_dir,
+            relocate::get_database_url,
+            idle::register_activity,
+            idle::set_idle_timeout,
+            idle::unlock,
+            disk_monitor::set_low_disk_threshold,
+            credentials::test_api_credential,
+            diagnostics::run_diagnostics,
+            diagnostics::get_app_info
         ])
         .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+            if let Ok(log_dir) = app.path().app_log_dir() {
+                crash::set_report_dir(log_dir);
+            }
+
+            if let Err(err) = logging::rotate(app.handle()) {
+                eprintln!("Failed to rotate log files: {}", err);
+            }
+            app.handle().plugin(logging::plugin(app.handle()))?;
+            paths::init(app.handle());
+            secrets::init_backend(app.handle());
+            notifications::init(app.handle());
+            db::auto_backup::start(app.handle());
+            idle::start(app.handle());
+            disk_monitor::start(app.handle());
+            window_state::restore(app.handle());
+            desktop::watch_system_theme(app.handle());
 
+            let mut devtools_opened = false;
+            if cfg!(debug_assertions) {
                 // Open devtools in debug mode
-                if let Some(window) = app.get_webview_window("main") {
+                if let Ok(window) = window_state::main_window(app.handle()) {
                     window.open_devtools();
+                    devtools_opened = true;
                 }
             }
+
+            // The plugin starts at trace level, so the startup line is logged
+            // before the (stricter) saved level takes over.
+            diagnostics::log_startup(app.handle(), devtools_opened);
+            logging::apply_saved_level(app.handle());
             Ok(())
         })
-        .run(tauri::generate_context!())
-    {
-        eprintln!("Failed to start JurisDesk: {}", e);
-        std::process::exit(1);
-    }
+        .build(tauri::generate_context!());
+
+    let app = match app {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("Failed to start JurisDesk: {}", e);
+            std::process::exit(1);
+        }
+    };
+    app.run(|app, event| {
+        if let tauri::RunEvent::Exit = event {
+            watch::stop_all(app);
+            db::checkpoint_on_exit(app);
+        }
+    });
 }
@@ -0,0 +1,321 @@
+//! Logging setup. The plugin dispatches everything and `log::set_max_level`
+//! does the filtering, so the level can change at runtime.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use log::LevelFilter;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_log::{fern, Target, TargetKind};
+
+use crate::db::run_blocking;
+use crate::paths::resolve_save_path;
+use crate::settings;
+
+/// Used when no level has been saved (or the saved one is invalid).
+pub const DEFAULT_LEVEL: LevelFilter = LevelFilter::Warn;
+
+/// Base name of the log file in the app log dir (`jurisdesk.log`). Rotated
+/// files are `jurisdesk.1.log` (newest) through `jurisdesk.{N-1}.log`.
+pub const LOG_FILE_NAME: &str = "jurisdesk";
+/// The live file is rotated before a record would take it past this size.
+const MAX_LOG_FILE_BYTES: u64 = 2 * 1024 * 1024;
+/// Files kept, counting the live one.
+const MAX_LOG_FILES: usize = 5;
+/// Oldest rotated files are deleted until everything fits under this cap.
+const MAX_TOTAL_LOG_BYTES: u64 = 8 * 1024 * 1024;
+
+/// The plugin, writing to stdout and to the live file in the log dir. The
+/// plugin's own file target only checks its size limit when it opens the
+/// file, so the file goes through [`LiveLog`] instead.
+pub fn plugin(app: &AppHandle) -> tauri::plugin::TauriPlugin<Wry> {
+    let mut targets = vec![Target::new(TargetKind::Stdout)];
+    match log_dir(app).and_then(|dir| LiveLog::open(&dir).map_err(|err| err.to_string())) {
+        Ok(live) => {
+            let output = fern::Output::writer(Box::new(live), "\n");
+            targets.push(Target::new(TargetKind::Dispatch(
+                fern::Dispatch::new().chain(output),
+            )));
+        }
+        Err(err) => eprintln!("Failed to open the log file: {}", err),
+    }
+    tauri_plugin_log::Builder::default()
+        .level(LevelFilter::Trace)
+        .targets(targets)
+        .build()
+}
+
+fn open_live(dir: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path(dir, 0))
+}
+
+/// The live log file. fern flushes after every record, so records are
+/// buffered and written whole, rotating first (see [`shift`]) whenever one
+/// would take the file past [`MAX_LOG_FILE_BYTES`].
+struct LiveLog {
+    dir: PathBuf,
+    file: File,
+    len: u64,
+    pending: Vec<u8>,
+}
+
+impl LiveLog {
+    fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = open_live(dir)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            len,
+            pending: Vec::new(),
+        })
+    }
+
+    // Logging from here would re-enter the logger, so problems go to stderr.
+    fn rotate(&mut self) {
+        if let Err(err) = shift(&self.dir) {
+            eprintln!("Failed to rotate log files: {}", err);
+            return;
+        }
+        match open_live(&self.dir) {
+            Ok(file) => {
+                self.file = file;
+                self.len = 0;
+            }
+            Err(err) => eprintln!("Failed to reopen the log file: {}", err),
+        }
+        if let Err(err) = trim(&self.dir) {
+            eprintln!("Failed to trim log files: {}", err);
+        }
+    }
+}
+
+impl Write for LiveLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.len > 0 && self.len + self.pending.len() as u64 > MAX_LOG_FILE_BYTES {
+            self.rotate();
+        }
+        let record = std::mem::take(&mut self.pending);
+        self.file.write_all(&record)?;
+        self.len += record.len() as u64;
+        self.file.flush()
+    }
+}
+
+pub fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path().app_log_dir().map_err(|err| err.to_string())
+}
+
+fn log_path(dir: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join(format!("{}.log", LOG_FILE_NAME))
+    } else {
+        dir.join(format!("{}.{}.log", LOG_FILE_NAME, index))
+    }
+}
+
+fn file_len(path: &Path) -> u64 {
+    fs::metadata(path).map(|meta| meta.len()).unwrap_or(0)
+}
+
+/// Rotated files as `(index, path)`, oldest last.
+fn rotated_files(dir: &Path) -> Vec<(usize, PathBuf)> {
+    let prefix = format!("{}.", LOG_FILE_NAME);
+    let mut files: Vec<(usize, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let index = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix(&prefix)?
+                .strip_suffix(".log")?
+                .parse()
+                .ok()?;
+            Some((index, path))
+        })
+        .collect();
+    files.sort_by_key(|(index, _)| *index);
+    files
+}
+
+/// Deletes rotated files beyond [`MAX_LOG_FILES`] or [`MAX_TOTAL_LOG_BYTES`]
+/// and returns how many were removed. The live file is never touched.
+fn trim(dir: &Path) -> Result<u32, String> {
+    let mut files = rotated_files(dir);
+    let mut total: u64 =
+        file_len(&log_path(dir, 0)) + files.iter().map(|(_, path)| file_len(path)).sum::<u64>();
+    let mut removed = 0;
+
+    while let Some((index, path)) = files.last() {
+        if *index < MAX_LOG_FILES && total <= MAX_TOTAL_LOG_BYTES {
+            break;
+        }
+        total = total.saturating_sub(file_len(path));
+        fs::remove_file(path).map_err(|err| err.to_string())?;
+        files.pop();
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Shifts `jurisdesk.log` to `jurisdesk.1.log`, `jurisdesk.1.log` to
+/// `jurisdesk.2.log` and so on.
+fn shift(dir: &Path) -> Result<(), String> {
+    for (index, path) in rotated_files(dir).into_iter().rev() {
+        fs::rename(&path, log_path(dir, index + 1)).map_err(|err| err.to_string())?;
+    }
+    fs::rename(log_path(dir, 0), log_path(dir, 1)).map_err(|err| err.to_string())
+}
+
+/// Rotates the live file if it's over the size limit and trims old files.
+/// Must run before the plugin opens the live file.
+pub fn rotate(app: &AppHandle) -> Result<(), String> {
+    let dir = log_dir(app)?;
+    if file_len(&log_path(&dir, 0)) > MAX_LOG_FILE_BYTES {
+        shift(&dir)?;
+    }
+    trim(&dir).map(|_| ())
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level.trim()).map_err(|_| {
+        format!(
+            "Invalid log level '{}': expected one of off, error, warn, info, debug, trace",
+            level
+        )
+    })
+}
+
+/// Applies the persisted level, falling back to [`DEFAULT_LEVEL`].
+pub fn apply_saved_level(app: &AppHandle) {
+    let level = match settings::load(app).log_level {
+        Some(saved) => parse_level(&saved).unwrap_or_else(|err| {
+            log::warn!("{}; using {}", err, DEFAULT_LEVEL);
+            DEFAULT_LEVEL
+        }),
+        None => DEFAULT_LEVEL,
+    };
+    log::set_max_level(level);
+}
+
+/// Changes the log level immediately and remembers it across restarts.
+#[tauri::command]
+pub fn set_log_level(app: AppHandle, level: String) -> Result<(), String> {
+    let filter = parse_level(&level)?;
+    settings::update(&app, |settings| {
+        settings.log_level = Some(filter.to_string().to_lowercase());
+    })?;
+    log::set_max_level(filter);
+    Ok(())
+}
+
+/// Deletes old rotated log files beyond the count/size caps and returns how
+/// many were removed.
+#[tauri::command]
+pub fn rotate_logs(app: AppHandle) -> Result<u32, String> {
+    trim(&log_dir(&app)?)
+}
+
+/// Most lines `tail_log` returns.
+const MAX_TAIL_LINES: u32 = 5000;
+const TAIL_BLOCK: u64 = 8 * 1024;
+
+/// Last `lines` lines of `path`, read backwards in blocks from the end.
+fn tail(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut start = file.metadata()?.len();
+    let mut buffer: Vec<u8> = Vec::new();
+    // `lines + 1` newlines guarantee the first wanted line is complete,
+    // whether or not the file ends with a newline.
+    let mut newlines = 0;
+    while start > 0 && newlines <= lines {
+        let block = TAIL_BLOCK.min(start);
+        start -= block;
+        file.seek(SeekFrom::Start(start))?;
+        let mut chunk = vec![0u8; block as usize];
+        file.read_exact(&mut chunk)?;
+        newlines += chunk.iter().filter(|&&byte| byte == b'\n').count();
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+    }
+
+    let text = String::from_utf8_lossy(&buffer);
+    let mut tail: Vec<String> = text.lines().rev().take(lines).map(String::from).collect();
+    tail.reverse();
+    Ok(tail)
+}
+
+/// The last `lines` lines (at most 5000) of the live log file, oldest first,
+/// for the in-app log viewer. Empty if nothing has been logged yet.
+#[tauri::command]
+pub async fn tail_log(app: AppHandle, lines: u32) -> Result<Vec<String>, String> {
+    let path = log_path(&log_dir(&app)?, 0);
+    let lines = lines.min(MAX_TAIL_LINES) as usize;
+    run_blocking(move || tail(&path, lines).map_err(|err| err.to_string())).await
+}
+
+fn zip_logs(dir: &Path, dest: &Path) -> Result<u32, String> {
+    let mut files = vec![log_path(dir, 0)];
+    files.extend(rotated_files(dir).into_iter().map(|(_, path)| path));
+
+    let out = BufWriter::new(File::create(dest).map_err(|err| err.to_string())?);
+    let mut zip = zip::ZipWriter::new(out);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut added = 0;
+    for path in files {
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.to_string()),
+        };
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        zip.start_file(name, options)
+            .map_err(|err| err.to_string())?;
+        io::copy(&mut file, &mut zip).map_err(|err| err.to_string())?;
+        added += 1;
+    }
+    zip.finish()
+        .map_err(|err| err.to_string())?
+        .into_inner()
+        .map_err(|err| err.to_string())?
+        .sync_all()
+        .map_err(|err| err.to_string())?;
+    Ok(added)
+}
+
+/// Zips the live and rotated log files into `dest` (relative to the app data
+/// dir, or a path from `prompt_save_path`) for attaching to a support
+/// ticket, and returns how many were added.
+#[tauri::command]
+pub async fn export_logs(app: AppHandle, dest: String) -> Result<u32, String> {
+    let dir = log_dir(&app)?;
+    let dest = resolve_save_path(&app, &dest)?;
+    run_blocking(move || {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        zip_logs(&dir, &dest)
+    })
+    .await
+}
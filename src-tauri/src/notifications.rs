@@ -0,0 +1,231 @@
+//! Deadline reminders that survive restarts. Pending notifications are kept
+//! in a JSON file in the app data dir and re-armed from `setup`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tauri::plugin::PermissionState;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+const STORE_FILE: &str = "scheduled-notifications.json";
+/// Timers wake at least this often and re-check the wall clock, so a machine
+/// that slept through a deadline still fires promptly after waking.
+const MAX_SLEEP: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ScheduledNotification {
+    id: String,
+    title: String,
+    body: String,
+    at_epoch_ms: i64,
+}
+
+#[derive(Default)]
+pub struct Scheduler(Mutex<HashMap<String, ScheduledNotification>>);
+
+impl Scheduler {
+    fn pending(&self) -> MutexGuard<'_, HashMap<String, ScheduledNotification>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::paths::data_dir(app).map(|dir| dir.join(STORE_FILE))
+}
+
+fn persist(
+    app: &AppHandle,
+    pending: &HashMap<String, ScheduledNotification>,
+) -> Result<(), String> {
+    let path = store_path(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let mut entries: Vec<&ScheduledNotification> = pending.values().collect();
+    entries.sort_by_key(|entry| entry.at_epoch_ms);
+    let raw = serde_json::to_string_pretty(&entries).map_err(|err| err.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, raw).map_err(|err| err.to_string())?;
+    fs::rename(&tmp, &path).map_err(|err| err.to_string())
+}
+
+fn fire(app: &AppHandle, id: &str) {
+    let scheduler = app.state::<Scheduler>();
+    let mut pending = scheduler.pending();
+    let Some(notification) = pending.remove(id) else {
+        return;
+    };
+    if let Err(err) = persist(app, &pending) {
+        log::warn!("Failed to persist scheduled notifications: {}", err);
+    }
+    drop(pending);
+
+    if let Err(err) = show(app, &notification.title, &notification.body) {
+        log::warn!("Failed to show scheduled notification {}: {}", id, err);
+    }
+}
+
+/// Shows a notification right away.
+pub fn show(app: &AppHandle, title: &str, body: &str) -> Result<(), String> {
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|err| err.to_string())
+}
+
+fn arm(app: AppHandle, id: String) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let due = match app.state::<Scheduler>().pending().get(&id) {
+                Some(notification) => notification.at_epoch_ms,
+                // Cancelled.
+                None => return,
+            };
+            let remaining = due - now_ms();
+            if remaining <= 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(remaining as u64).min(MAX_SLEEP)).await;
+        }
+        fire(&app, &id);
+    });
+}
+
+/// Loads persisted notifications and re-arms them. Ones whose time passed
+/// while the app was closed are dropped rather than fired late.
+pub fn init(app: &AppHandle) {
+    let stored: Vec<ScheduledNotification> = store_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+
+    let now = now_ms();
+    let total = stored.len();
+    let pending: HashMap<String, ScheduledNotification> = stored
+        .into_iter()
+        .filter(|notification| notification.at_epoch_ms > now)
+        .map(|notification| (notification.id.clone(), notification))
+        .collect();
+
+    if pending.len() != total {
+        log::info!(
+            "Dropped {} scheduled notification(s) that expired while closed",
+            total - pending.len()
+        );
+        if let Err(err) = persist(app, &pending) {
+            log::warn!("Failed to persist scheduled notifications: {}", err);
+        }
+    }
+
+    let ids: Vec<String> = pending.keys().cloned().collect();
+    app.manage(Scheduler(Mutex::new(pending)));
+    for id in ids {
+        arm(app.clone(), id);
+    }
+}
+
+/// Schedules a notification and returns its id. A time in the past fires
+/// immediately.
+#[tauri::command]
+pub fn schedule_notification(
+    app: AppHandle,
+    scheduler: State<'_, Scheduler>,
+    title: String,
+    body: String,
+    at_epoch_ms: i64,
+) -> Result<String, String> {
+    if title.trim().is_empty() {
+        return Err("Notification title must not be empty".to_string());
+    }
+
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let id: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    {
+        let mut pending = scheduler.pending();
+        pending.insert(
+            id.clone(),
+            ScheduledNotification {
+                id: id.clone(),
+                title,
+                body,
+                at_epoch_ms,
+            },
+        );
+        if let Err(err) = persist(&app, &pending) {
+            pending.remove(&id);
+            return Err(err);
+        }
+    }
+
+    arm(app, id.clone());
+    Ok(id)
+}
+
+/// Cancels a pending notification. Unknown ids (already fired or cancelled)
+/// are not an error.
+#[tauri::command]
+pub fn cancel_scheduled_notification(
+    app: AppHandle,
+    scheduler: State<'_, Scheduler>,
+    id: String,
+) -> Result<(), String> {
+    let mut pending = scheduler.pending();
+    if pending.remove(&id).is_some() {
+        persist(&app, &pending)?;
+    }
+    Ok(())
+}
+
+/// Web Notification API names, which the frontend already understands.
+fn permission_name(state: PermissionState) -> &'static str {
+    match state {
+        PermissionState::Granted => "granted",
+        PermissionState::Denied => "denied",
+        PermissionState::Prompt | PermissionState::PromptWithRationale => "default",
+    }
+}
+
+/// `"granted"`, `"denied"` or `"default"` (not asked yet). Desktop platforms
+/// that don't gate notifications always report `"granted"`.
+#[tauri::command]
+pub fn notification_permission_state(app: AppHandle) -> Result<String, String> {
+    let state = app
+        .notification()
+        .permission_state()
+        .map_err(|err| err.to_string())?;
+    Ok(permission_name(state).to_string())
+}
+
+/// Shows the OS permission prompt if needed and returns whether notifications
+/// are allowed. Doesn't prompt where permission is implicitly granted.
+#[tauri::command]
+pub fn request_notification_permission(app: AppHandle) -> Result<bool, String> {
+    let notification = app.notification();
+    let state = notification
+        .permission_state()
+        .map_err(|err| err.to_string())?;
+    let state = match state {
+        PermissionState::Granted | PermissionState::Denied => state,
+        _ => notification
+            .request_permission()
+            .map_err(|err| err.to_string())?,
+    };
+    Ok(state == PermissionState::Granted)
+}
@@ -0,0 +1,285 @@
+//! Locates the app data directory and confines paths supplied by the
+//! webview to it.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_fs::FsExt;
+
+/// Pointer to a data directory moved by `relocate_data_directory`, kept in
+/// the app config dir so it is found before the data dir is known.
+const LOCATION_FILE: &str = "data-location.json";
+
+/// The relocated data dir, loaded by [`init`]; `None` means the OS default.
+static DATA_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+#[derive(Serialize, Deserialize)]
+struct DataLocation {
+    data_dir: PathBuf,
+}
+
+/// The directory set by `relocate_data_directory`, if the data was moved.
+pub fn relocated_data_dir() -> Option<PathBuf> {
+    DATA_DIR
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Where the app keeps its data: the directory set by
+/// `relocate_data_directory`, or the platform's app data dir.
+pub fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    match relocated_data_dir() {
+        Some(dir) => Ok(dir),
+        None => app.path().app_data_dir().map_err(|err| err.to_string()),
+    }
+}
+
+pub fn location_file(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(LOCATION_FILE))
+        .map_err(|err| err.to_string())
+}
+
+/// Reads the data dir pointer. Call first thing in `setup`, before anything
+/// touches the data dir. A pointer to a directory that no longer exists
+/// (e.g. an unplugged drive) is ignored with an error in the log rather than
+/// silently starting over in a new, empty directory there.
+pub fn init(app: &AppHandle) {
+    let Ok(path) = location_file(app) else {
+        return;
+    };
+    let Ok(raw) = fs::read_to_string(&path) else {
+        return;
+    };
+    match serde_json::from_str::<DataLocation>(&raw) {
+        Ok(location) if location.data_dir.is_dir() => set_data_dir(app, location.data_dir),
+        Ok(location) => log::error!(
+            "Relocated data directory {} is missing; using the default location",
+            location.data_dir.display()
+        ),
+        Err(err) => log::warn!("Ignoring unreadable {}: {}", path.display(), err),
+    }
+}
+
+/// Also lets the webview's fs plugin reach `dir`, which the `$APPDATA` scope
+/// in the capabilities no longer covers.
+fn set_data_dir(app: &AppHandle, dir: PathBuf) {
+    if let Some(scope) = app.try_fs_scope() {
+        if let Err(err) = scope.allow_directory(&dir, true) {
+            log::warn!("Failed to allow {} in the fs scope: {}", dir.display(), err);
+        }
+    }
+    *DATA_DIR
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(dir);
+}
+
+/// Points the app at `dir` from now on and on later launches.
+pub fn save_data_dir(app: &AppHandle, dir: &Path) -> Result<(), String> {
+    let path = location_file(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let location = DataLocation {
+        data_dir: dir.to_path_buf(),
+    };
+    let raw = serde_json::to_string_pretty(&location).map_err(|err| err.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, raw).map_err(|err| err.to_string())?;
+    fs::rename(&tmp, &path).map_err(|err| err.to_string())?;
+    set_data_dir(app, dir.to_path_buf());
+    Ok(())
+}
+
+/// Joins `relative` onto the app data dir and rejects anything that would
+/// land outside it: absolute paths, `..` components, and symlinks pointing
+/// elsewhere (checked after canonicalization). The target itself doesn't
+/// need to exist yet, but must not be a symlink, dangling or not, since
+/// creating the file would write through it.
+pub fn resolve_app_path(app: &AppHandle, relative: &str) -> Result<PathBuf, String> {
+    let base = data_dir(app)?;
+    resolve_within(&base, relative)
+}
+
+fn resolve_within(base: &Path, relative: &str) -> Result<PathBuf, String> {
+    if relative.trim().is_empty() {
+        return Err("Path must not be empty".to_string());
+    }
+
+    let relative_path = Path::new(relative);
+    for component in relative_path.components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            Component::ParentDir => {
+                return Err(format!("Path must not contain '..': {}", relative));
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(format!("Absolute paths are not allowed: {}", relative));
+            }
+        }
+    }
+
+    fs::create_dir_all(base).map_err(|err| err.to_string())?;
+    let base = dunce::canonicalize(base).map_err(|err| err.to_string())?;
+
+    let joined = base.join(relative_path);
+    if fs::symlink_metadata(&joined).is_ok_and(|meta| meta.file_type().is_symlink()) {
+        return Err(format!("Path must not be a symlink: {}", relative));
+    }
+
+    // Canonicalize the deepest existing ancestor so symlinks are resolved,
+    // then re-append the components that don't exist yet. A dangling symlink
+    // counts as existing, so canonicalizing it fails instead of passing.
+    let mut existing = joined.as_path();
+    let mut missing = Vec::new();
+    while fs::symlink_metadata(existing).is_err() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                missing.push(name.to_owned());
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = dunce::canonicalize(existing).map_err(|err| err.to_string())?;
+    resolved.extend(missing.iter().rev());
+
+    if !resolved.starts_with(&base) {
+        return Err(format!("Path escapes the app data directory: {}", relative));
+    }
+    Ok(resolved)
+}
+
+/// Absolute paths the user chose in the save or folder dialog
+/// (`prompt_save_path`, `prompt_folder_path`). Export commands accept these
+/// in place of a path under the data dir; each approval is spent by the
+/// command that uses it.
+#[derive(Default)]
+pub struct ApprovedSavePaths(Mutex<HashSet<PathBuf>>);
+
+impl ApprovedSavePaths {
+    pub fn approve(&self, path: PathBuf) {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(path);
+    }
+
+    fn take(&self, path: &Path) -> bool {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(path)
+    }
+}
+
+/// Resolves an export destination: an absolute path returned by the save
+/// dialog, or a path under the app data dir as for [`resolve_app_path`].
+/// Any other absolute path is rejected, so the webview can't write files
+/// wherever it likes.
+pub fn resolve_save_path(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let candidate = Path::new(path);
+    if !candidate.is_absolute() {
+        return resolve_app_path(app, path);
+    }
+    if app.state::<ApprovedSavePaths>().take(candidate) {
+        Ok(candidate.to_path_buf())
+    } else {
+        Err(format!(
+            "Absolute paths must be chosen in a file dialog: {}",
+            path
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory under the system temp dir.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("jurisdesk-paths-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dunce::canonicalize(dir).unwrap()
+    }
+
+    #[test]
+    fn resolves_paths_inside_the_base() {
+        let base = scratch_dir("inside");
+        fs::create_dir_all(base.join("documents")).unwrap();
+        assert_eq!(
+            resolve_within(&base, "documents/1/file.pdf").unwrap(),
+            base.join("documents").join("1").join("file.pdf")
+        );
+        assert_eq!(
+            resolve_within(&base, "./notes.txt").unwrap(),
+            base.join("notes.txt")
+        );
+    }
+
+    #[test]
+    fn rejects_empty_parent_and_absolute_paths() {
+        let base = scratch_dir("reject");
+        assert_eq!(
+            resolve_within(&base, " ").unwrap_err(),
+            "Path must not be empty"
+        );
+        assert_eq!(
+            resolve_within(&base, "documents/../../secret").unwrap_err(),
+            "Path must not contain '..': documents/../../secret"
+        );
+        let absolute = base.join("file.txt");
+        let absolute = absolute.to_str().unwrap();
+        assert_eq!(
+            resolve_within(&base, absolute).unwrap_err(),
+            format!("Absolute paths are not allowed: {}", absolute)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_a_symlinked_parent_pointing_outside() {
+        let base = scratch_dir("parent");
+        let outside = scratch_dir("parent-outside");
+        std::os::unix::fs::symlink(&outside, base.join("link")).unwrap();
+        assert_eq!(
+            resolve_within(&base, "link/file.txt").unwrap_err(),
+            "Path escapes the app data directory: link/file.txt"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinked_leaves_even_when_dangling() {
+        let base = scratch_dir("leaf");
+        let outside = scratch_dir("leaf-outside");
+        std::os::unix::fs::symlink(outside.join("missing.txt"), base.join("dangling")).unwrap();
+        assert_eq!(
+            resolve_within(&base, "dangling").unwrap_err(),
+            "Path must not be a symlink: dangling"
+        );
+        fs::write(base.join("target.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(base.join("target.txt"), base.join("inner")).unwrap();
+        assert_eq!(
+            resolve_within(&base, "inner").unwrap_err(),
+            "Path must not be a symlink: inner"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_paths_below_a_dangling_symlink() {
+        let base = scratch_dir("below");
+        let outside = scratch_dir("below-outside");
+        std::os::unix::fs::symlink(outside.join("gone"), base.join("dir")).unwrap();
+        assert!(resolve_within(&base, "dir/file.txt").is_err());
+    }
+}
@@ -0,0 +1,45 @@
+//! Progress events for long-running commands.
+//!
+//! Commands take a caller-chosen `task_id` and report steps on the shared
+//! `task-progress` channel, so the frontend subscribes once and filters by id.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+pub const TASK_PROGRESS_EVENT: &str = "task-progress";
+
+#[derive(Clone, Serialize)]
+struct TaskProgress<'a> {
+    task_id: &'a str,
+    current: u64,
+    total: u64,
+    message: &'a str,
+}
+
+/// Cheap to clone and `Send`, so it can move into `run_blocking` work.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    app: AppHandle,
+    task_id: String,
+}
+
+impl ProgressReporter {
+    pub fn new(app: &AppHandle, task_id: impl Into<String>) -> Self {
+        Self {
+            app: app.clone(),
+            task_id: task_id.into(),
+        }
+    }
+
+    pub fn report(&self, current: u64, total: u64, message: &str) {
+        let payload = TaskProgress {
+            task_id: &self.task_id,
+            current,
+            total,
+            message,
+        };
+        if let Err(err) = self.app.emit(TASK_PROGRESS_EVENT, payload) {
+            log::warn!("Failed to emit {}: {}", TASK_PROGRESS_EVENT, err);
+        }
+    }
+}
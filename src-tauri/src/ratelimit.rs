@@ -0,0 +1,109 @@
+//! Token-bucket limits for commands that reach the network, so a buggy or
+//! compromised webview can't use the app to flood a provider.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::CommandError;
+
+/// A bucket of `capacity` tokens, one regained every `refill_every`. Each
+/// call spends a token; buckets are kept per `command`.
+pub struct Limit {
+    pub command: &'static str,
+    pub capacity: u32,
+    pub refill_every: Duration,
+}
+
+/// Five checks in a burst, then one every 12 seconds.
+pub const CREDENTIAL_TEST: Limit = Limit {
+    command: "test_api_credential",
+    capacity: 5,
+    refill_every: Duration::from_secs(12),
+};
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Default)]
+pub struct RateLimiter(Mutex<HashMap<&'static str, Bucket>>);
+
+impl RateLimiter {
+    /// Spends a token for `limit.command`, or fails with `TooManyRequests`.
+    pub fn check(&self, limit: &Limit) -> Result<(), CommandError> {
+        self.check_at(limit, Instant::now())
+    }
+
+    fn check_at(&self, limit: &Limit, now: Instant) -> Result<(), CommandError> {
+        let mut buckets = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let capacity = f64::from(limit.capacity);
+        let bucket = buckets.entry(limit.command).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() / limit.refill_every.as_secs_f64())
+            .min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            let wait = limit.refill_every.mul_f64(1.0 - bucket.tokens);
+            return Err(CommandError::TooManyRequests(format!(
+                "Too many {} calls; try again in {}s",
+                limit.command,
+                wait.as_secs().max(1)
+            )));
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_LIMIT: Limit = Limit {
+        command: "test_command",
+        capacity: 3,
+        refill_every: Duration::from_secs(10),
+    };
+
+    #[test]
+    fn rejects_calls_past_capacity_until_refilled() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        for _ in 0..TEST_LIMIT.capacity {
+            assert!(limiter.check_at(&TEST_LIMIT, start).is_ok());
+        }
+
+        let err = limiter.check_at(&TEST_LIMIT, start).unwrap_err();
+        assert_eq!(err.code(), "too_many_requests");
+        assert!(matches!(err, CommandError::TooManyRequests(_)));
+
+        let later = start + TEST_LIMIT.refill_every;
+        assert!(limiter.check_at(&TEST_LIMIT, later).is_ok());
+        assert!(limiter.check_at(&TEST_LIMIT, later).is_err());
+    }
+
+    #[test]
+    fn buckets_are_per_command() {
+        let limiter = RateLimiter::default();
+        let other = Limit {
+            command: "other_command",
+            ..TEST_LIMIT
+        };
+        let now = Instant::now();
+        for _ in 0..TEST_LIMIT.capacity {
+            limiter.check_at(&TEST_LIMIT, now).unwrap();
+        }
+        assert!(limiter.check_at(&TEST_LIMIT, now).is_err());
+        assert!(limiter.check_at(&other, now).is_ok());
+    }
+}
@@ -0,0 +1,409 @@
+//! Moving the app data directory to another volume.
+//!
+//! Files are copied and verified first, the primary database among them, and
+//! the attachment paths stored in the copied database are rewritten to the
+//! new location. The pointer the app reads at startup (see `paths`) is
+//! switched only after all of that succeeded, and the originals are deleted
+//! last. An interrupted run leaves the source untouched and can simply be
+//! started again, skipping files already copied intact.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::files::checksum;
+use crate::paths;
+use crate::progress::ProgressReporter;
+use crate::watch;
+
+/// Left in the destination while a relocation is in progress, naming the
+/// source, so a second run recognizes its own partial copy.
+const MARKER_FILE: &str = ".jurisdesk-relocation";
+const CHECKSUM: &str = "blake3";
+
+#[derive(Serialize, Deserialize)]
+struct Marker {
+    source: PathBuf,
+}
+
+/// Tables whose `file_path` holds absolute paths into the data dir.
+const PATH_TABLES: &[&str] = &["documents", "chat_attachments"];
+
+struct DataFile {
+    source: PathBuf,
+    /// Where the file goes, relative to the new data dir.
+    relative: PathBuf,
+    size: u64,
+}
+
+/// Files the directory walk leaves alone: the location pointer, which stays
+/// in the config dir, and the primary database, which is moved separately
+/// (see [`primary_database`]) since it may live outside the data dir.
+fn pinned_files(app: &AppHandle) -> Result<Vec<PathBuf>, String> {
+    let mut pinned = vec![paths::location_file(app)?];
+    let primary = db::primary_db_path(app)?;
+    for suffix in ["-wal", "-shm", "-journal"] {
+        pinned.push(db::sidecar_path(&primary, suffix));
+    }
+    pinned.push(primary);
+    // Compared against paths found under the canonicalized data dir.
+    Ok(pinned
+        .into_iter()
+        .map(|path| match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => dunce::canonicalize(dir)
+                .map(|dir| dir.join(name))
+                .unwrap_or(path),
+            _ => path,
+        })
+        .collect())
+}
+
+/// Every regular file under `root`, recursively, except the pinned ones.
+/// Symlinks are skipped; they may point anywhere.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    pinned: &[PathBuf],
+    files: &mut Vec<DataFile>,
+) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|err| format!("{}: {}", dir.display(), err))? {
+        let entry = entry.map_err(|err| err.to_string())?;
+        let path = entry.path();
+        let file_type = entry.file_type().map_err(|err| err.to_string())?;
+        if file_type.is_dir() {
+            collect_files(root, &path, pinned, files)?;
+        } else if file_type.is_file() && !pinned.contains(&path) {
+            let size = entry.metadata().map_err(|err| err.to_string())?.len();
+            let relative = path
+                .strip_prefix(root)
+                .map_err(|err| err.to_string())?
+                .to_path_buf();
+            files.push(DataFile {
+                source: path,
+                relative,
+                size,
+            });
+        } else if file_type.is_symlink() {
+            log::warn!("Not relocating symlink {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// The primary database, checkpointed so it can be copied as a single file.
+/// `None` before the frontend has created it.
+fn primary_database(app: &AppHandle) -> Result<Option<DataFile>, String> {
+    let path = db::primary_db_path(app)?;
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let conn = db::open(&path)?;
+    let (busy, _, _) = db::checkpoint_truncate(&conn)?;
+    if busy != 0 {
+        return Err("The database is in use; close it and try again".to_string());
+    }
+    drop(conn);
+    let size = fs::metadata(&path).map_err(|err| err.to_string())?.len();
+    Ok(Some(DataFile {
+        source: path,
+        relative: PathBuf::from(db::PRIMARY_DB_FILE),
+        size,
+    }))
+}
+
+fn is_sqlite(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|()| &header == db::SQLITE_HEADER)
+}
+
+/// Fails if any of the databases being moved is written to right now.
+fn ensure_no_writers(files: &[DataFile]) -> Result<(), String> {
+    for file in files {
+        if !is_sqlite(&file.source) {
+            continue;
+        }
+        let conn = db::open(&file.source)?;
+        conn.busy_timeout(Duration::ZERO)
+            .map_err(|err| err.to_string())?;
+        match conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;") {
+            Ok(()) => {}
+            Err(err) if db::is_busy(&err) => {
+                return Err(format!(
+                    "Database {} is in use; close it and try again",
+                    file.relative.display()
+                ))
+            }
+            Err(err) => return Err(err.to_string()),
+        }
+    }
+    Ok(())
+}
+
+/// The destination must be empty apart from pinned files, or hold the
+/// marker of an earlier, interrupted move from the same source.
+fn check_destination(source: &Path, dest: &Path, pinned: &[PathBuf]) -> Result<(), String> {
+    let marker = dest.join(MARKER_FILE);
+    if let Ok(raw) = fs::read_to_string(&marker) {
+        let previous: Marker = serde_json::from_str(&raw).map_err(|err| err.to_string())?;
+        if previous.source == source {
+            log::info!("Resuming relocation into {}", dest.display());
+            return Ok(());
+        }
+    }
+    let occupied = fs::read_dir(dest)
+        .map_err(|err| err.to_string())?
+        .filter_map(Result::ok)
+        .any(|entry| !pinned.contains(&entry.path()));
+    if occupied {
+        return Err(format!("Destination is not empty: {}", dest.display()));
+    }
+    let raw = serde_json::to_string(&Marker {
+        source: source.to_path_buf(),
+    })
+    .map_err(|err| err.to_string())?;
+    fs::write(&marker, raw).map_err(|err| err.to_string())
+}
+
+/// Copies `src` to `dest` unless an identical copy is already there (from an
+/// interrupted run), then checks the copy against the source.
+fn copy_verified(src: &Path, dest: &Path) -> Result<(), String> {
+    let expected = checksum(src, CHECKSUM)?;
+    if dest.is_file() && checksum(dest, CHECKSUM)? == expected {
+        return Ok(());
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let tmp = db::sidecar_path(dest, ".partial");
+    fs::copy(src, &tmp).map_err(|err| format!("{}: {}", src.display(), err))?;
+    fs::rename(&tmp, dest).map_err(|err| err.to_string())?;
+    if checksum(dest, CHECKSUM)? != expected {
+        return Err(format!(
+            "Copy of {} does not match the original (was it changed during the move?); run the move again",
+            src.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Points the `file_path`s under any of `old_dirs` at `new_dir` instead, in a
+/// single transaction on the moved copy of the primary database. Returns how
+/// many rows changed.
+fn rewrite_file_paths(
+    db_path: &Path,
+    old_dirs: &[PathBuf],
+    new_dir: &Path,
+) -> Result<usize, String> {
+    let new_dir = new_dir
+        .to_str()
+        .ok_or_else(|| format!("Path is not valid UTF-8: {}", new_dir.display()))?;
+    let mut separators = vec!['/', std::path::MAIN_SEPARATOR];
+    separators.dedup();
+
+    let mut conn = db::open(db_path)?;
+    let tx = conn.transaction().map_err(|err| err.to_string())?;
+    let mut rewritten = 0;
+    for table in PATH_TABLES {
+        let exists: bool = tx
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )
+            .map_err(|err| err.to_string())?;
+        if !exists {
+            continue;
+        }
+        let sql = format!(
+            "UPDATE \"{}\" SET file_path = ?2 || substr(file_path, length(?1) + 1) \
+             WHERE substr(file_path, 1, length(?1)) = ?1",
+            table
+        );
+        for old_dir in old_dirs.iter().filter_map(|dir| dir.to_str()) {
+            for separator in &separators {
+                let old_prefix = format!("{}{}", old_dir, separator);
+                let new_prefix = format!("{}{}", new_dir, separator);
+                rewritten += tx
+                    .execute(&sql, [&old_prefix, &new_prefix])
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+    }
+    tx.commit().map_err(|err| err.to_string())?;
+    Ok(rewritten)
+}
+
+/// Deletes the moved files and then any directories under `root` left
+/// empty. Failures are logged: the data is already safe in its new location.
+fn remove_originals(root: &Path, files: &[DataFile]) {
+    for file in files {
+        if let Err(err) = fs::remove_file(&file.source) {
+            log::warn!("Failed to remove {}: {}", file.source.display(), err);
+        }
+    }
+    let mut dirs: Vec<PathBuf> = files
+        .iter()
+        .flat_map(|file| file.relative.ancestors().skip(1))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| root.join(dir))
+        .collect();
+    // Deepest first, so parents are empty by the time they're reached.
+    dirs.sort_by(|a, b| {
+        let depth = |dir: &PathBuf| dir.components().count();
+        depth(b).cmp(&depth(a)).then_with(|| a.cmp(b))
+    });
+    dirs.dedup();
+    for dir in dirs {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+fn relocate(
+    app: &AppHandle,
+    source: &Path,
+    dest: &Path,
+    progress: &ProgressReporter,
+) -> Result<(), String> {
+    let pinned = pinned_files(app)?;
+    let mut files = Vec::new();
+    collect_files(source, source, &pinned, &mut files)?;
+    let primary = primary_database(app)?;
+    if let Some(primary) = &primary {
+        // A stray file of the same name in the data dir would overwrite it.
+        files.retain(|file| file.relative != primary.relative);
+    }
+    files.extend(primary);
+    ensure_no_writers(&files)?;
+
+    fs::create_dir_all(dest).map_err(|err| err.to_string())?;
+    let dest = dunce::canonicalize(dest).map_err(|err| err.to_string())?;
+    check_destination(source, &dest, &pinned)?;
+
+    // Files already copied by an interrupted run don't need space again.
+    let needed: u64 = files
+        .iter()
+        .filter(|file| {
+            fs::metadata(dest.join(&file.relative)).map_or(true, |meta| meta.len() != file.size)
+        })
+        .map(|file| file.size)
+        .sum();
+    let available = fs4::available_space(&dest).map_err(|err| err.to_string())?;
+    if needed > available {
+        return Err(format!(
+            "Not enough free space in {}: {} MB needed, {} MB available",
+            dest.display(),
+            needed.div_ceil(1024 * 1024),
+            available / (1024 * 1024)
+        ));
+    }
+
+    let total: u64 = files.iter().map(|file| file.size).sum();
+    let mut done = 0;
+    for file in &files {
+        progress.report(done, total, &format!("Copying {}", file.relative.display()));
+        copy_verified(&file.source, &dest.join(&file.relative))?;
+        done += file.size;
+    }
+
+    let primary_source = db::primary_db_path(app)?;
+    let moved_primary = dest.join(db::PRIMARY_DB_FILE);
+    if files.iter().any(|file| file.source == primary_source) {
+        // The frontend stored paths under the uncanonicalized data dir.
+        let mut old_dirs = vec![paths::data_dir(app)?, source.to_path_buf()];
+        old_dirs.dedup();
+        let rewritten = rewrite_file_paths(&moved_primary, &old_dirs, &dest)?;
+        log::info!("Rewrote {} stored file paths", rewritten);
+    }
+
+    paths::save_data_dir(app, &dest)?;
+    log::info!(
+        "Data directory moved from {} to {}",
+        source.display(),
+        dest.display()
+    );
+    progress.report(total, total, "Removing original files");
+    remove_originals(source, &files);
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let sidecar = db::sidecar_path(&primary_source, suffix);
+        if let Err(err) = fs::remove_file(&sidecar) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove {}: {}", sidecar.display(), err);
+            }
+        }
+    }
+    if let Err(err) = fs::remove_file(dest.join(MARKER_FILE)) {
+        log::warn!("Failed to remove relocation marker: {}", err);
+    }
+    Ok(())
+}
+
+/// Moves the app data directory (databases, attachments, settings, backups)
+/// and the primary database to `new_dir`, an absolute path the user picked,
+/// and uses it from then on. The frontend must close its connection first
+/// and afterwards load the database from `get_database_url`. Refuses while a
+/// database is being written to, or when `new_dir` isn't empty or lacks the
+/// space. Safe to run again after an interruption.
+///
+/// Folder watches are stopped, since their paths go away; the frontend sets
+/// them up again.
+#[tauri::command]
+pub async fn relocate_data_directory(
+    app: AppHandle,
+    new_dir: String,
+    task_id: Option<String>,
+) -> Result<(), String> {
+    if db::primary_db_is_open(&app).await {
+        return Err("The database is open; close it before moving the data directory".to_string());
+    }
+    let dest = PathBuf::from(new_dir.trim());
+    if !dest.is_absolute() {
+        return Err(format!(
+            "New location must be an absolute path: {}",
+            new_dir
+        ));
+    }
+    let source = paths::data_dir(&app)?;
+    fs::create_dir_all(&source).map_err(|err| err.to_string())?;
+    let source = dunce::canonicalize(&source).map_err(|err| err.to_string())?;
+    let resolved_dest = match dunce::canonicalize(&dest) {
+        Ok(dest) => dest,
+        Err(_) => dest.clone(),
+    };
+    if resolved_dest.starts_with(&source) || source.starts_with(&resolved_dest) {
+        return Err(format!(
+            "New location must not be inside the current data directory or contain it: {}",
+            new_dir
+        ));
+    }
+
+    watch::stop_all(&app);
+    let progress = ProgressReporter::new(
+        &app,
+        task_id.unwrap_or_else(|| "relocate_data_directory".into()),
+    );
+    db::run_blocking(move || relocate(&app, &source, &dest, &progress)).await
+}
+
+/// The current data directory, for the frontend to build file paths on
+/// instead of `appDataDir()`.
+#[tauri::command]
+pub fn get_data_dir(app: AppHandle) -> Result<String, String> {
+    let dir = paths::data_dir(&app)?;
+    dir.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("Path is not valid UTF-8: {}", dir.display()))
+}
+
+/// The connection URL the frontend passes to `Database.load` for the primary
+/// database, which moves along with the data directory.
+#[tauri::command]
+pub fn get_database_url(app: AppHandle) -> Result<String, String> {
+    db::primary_db_url(&app)
+}
@@ -0,0 +1,97 @@
+//! A random key per installation for data that never leaves this machine,
+//! kept in the secret store so every feature encrypts under the same key
+//! instead of deriving its own. [`DeviceKey::seal`] and [`DeviceKey::open`]
+//! are the way to use it; the automatic backup passphrase is stored sealed.
+
+use std::sync::{Mutex, MutexGuard};
+
+use base64::Engine as _;
+use tauri::State;
+
+use crate::crypto::{self, KEY_LEN};
+use crate::error::CommandError;
+
+const DEVICE_KEY_SECRET: &str = "device-key";
+/// Marks values written by [`DeviceKey::seal`].
+const SEALED_PREFIX: &str = "jd-dk1:";
+
+/// The device key once loaded. The mutex is held from the lookup to the
+/// write on first run, so concurrent callers can't store two different keys.
+#[derive(Default)]
+pub struct DeviceKey(Mutex<Option<[u8; KEY_LEN]>>);
+
+impl DeviceKey {
+    fn cached(&self) -> MutexGuard<'_, Option<[u8; KEY_LEN]>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The key, generated and stored the first time it's needed.
+    pub fn get(&self) -> Result<[u8; KEY_LEN], CommandError> {
+        let mut cached = self.cached();
+        if let Some(key) = *cached {
+            return Ok(key);
+        }
+        let engine = base64::engine::general_purpose::STANDARD;
+        let key = match super::read_internal(DEVICE_KEY_SECRET)? {
+            Some(encoded) => engine
+                .decode(encoded)
+                .ok()
+                .and_then(|bytes| <[u8; KEY_LEN]>::try_from(bytes).ok())
+                .ok_or_else(|| CommandError::Backend("Stored device key is invalid".to_string()))?,
+            None => {
+                let key: [u8; KEY_LEN] = crypto::random_bytes();
+                super::write_internal(DEVICE_KEY_SECRET, &engine.encode(key))?;
+                log::info!("Generated a new device key");
+                key
+            }
+        };
+        *cached = Some(key);
+        Ok(key)
+    }
+
+    /// Encrypts `plaintext` under the device key, as text for the secret
+    /// store. `context` (e.g. the secret's name) is authenticated with it, so
+    /// a sealed value can't be passed off as another.
+    pub fn seal(&self, context: &str, plaintext: &str) -> Result<String, CommandError> {
+        let key = self.get()?;
+        let sealed = crypto::encrypt(&key, plaintext.as_bytes(), context.as_bytes())
+            .map_err(CommandError::Backend)?;
+        let engine = base64::engine::general_purpose::STANDARD;
+        Ok(format!("{}{}", SEALED_PREFIX, engine.encode(sealed)))
+    }
+
+    /// Reverses [`DeviceKey::seal`]. Values stored before they were sealed
+    /// come back unchanged.
+    pub fn open(&self, context: &str, stored: &str) -> Result<String, CommandError> {
+        let Some(encoded) = stored.strip_prefix(SEALED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let engine = base64::engine::general_purpose::STANDARD;
+        let data = engine
+            .decode(encoded)
+            .map_err(|_| CommandError::Corrupt("Sealed value is not valid base64".to_string()))?;
+        let key = self.get()?;
+        let plaintext =
+            crypto::decrypt(&key, &data, context.as_bytes()).map_err(CommandError::Corrupt)?;
+        String::from_utf8(plaintext)
+            .map_err(|_| CommandError::Corrupt("Sealed value is not valid UTF-8".to_string()))
+    }
+
+    /// Drops the cached copy; the next [`DeviceKey::get`] reads it again.
+    pub fn forget(&self) {
+        let mut cached = self.cached();
+        if let Some(key) = cached.as_mut() {
+            key.fill(0);
+        }
+        *cached = None;
+    }
+}
+
+/// Creates the device key if this installation doesn't have one yet. Safe to
+/// call on every start.
+#[tauri::command]
+pub fn ensure_device_key(device_key: State<'_, DeviceKey>) -> Result<(), CommandError> {
+    device_key.get().map(|_| ())
+}
@@ -0,0 +1,797 @@
+//! Secret storage backed by the OS keychain (via the `keyring` crate), or an
+//! encrypted file when no keychain is available (see [`store`]).
+
+pub mod device;
+pub mod store;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::crypto;
+use crate::error::CommandError;
+use crate::paths::{resolve_app_path, resolve_save_path};
+use store::{EncryptedFileStore, KeyringStore, SecretStore};
+
+/// Service for secrets the backend keeps for its own use (e.g. the automatic
+/// backup passphrase), separate from the frontend's services and index.
+const INTERNAL_SERVICE: &str = "jurisdesk-internal";
+
+pub(crate) fn read_internal(name: &str) -> Result<Option<String>, CommandError> {
+    read_value(INTERNAL_SERVICE, name)
+}
+
+pub(crate) fn write_internal(name: &str, value: &str) -> Result<(), CommandError> {
+    let _guard = write_guard();
+    write_value(INTERNAL_SERVICE, name, value)
+}
+
+/// Backend chosen by [`init_backend`]; the keyring until then.
+static STORE: OnceLock<Box<dyn SecretStore>> = OnceLock::new();
+
+fn store() -> &'static dyn SecretStore {
+    STORE.get().map(Box::as_ref).unwrap_or(&KeyringStore)
+}
+
+/// Why the OS keychain was rejected at startup, if it was.
+static KEYCHAIN_PROBE_ERROR: OnceLock<String> = OnceLock::new();
+
+/// Drops keys and decrypted secrets the backend caches in memory.
+pub fn forget_cached() {
+    store().forget_cached();
+}
+
+/// Name of the active backend, for logs and diagnostics.
+pub fn backend_name() -> &'static str {
+    store().name()
+}
+
+/// Probes the OS keychain and falls back to the encrypted file store if it
+/// can't be used. Call once during setup, after logging is initialized.
+pub fn init_backend(app: &AppHandle) {
+    let probe = KeyringStore::probe();
+    if let Err(err) = &probe {
+        let _ = KEYCHAIN_PROBE_ERROR.set(err.to_string());
+    }
+    let backend: Box<dyn SecretStore> = match probe {
+        Ok(()) => Box::new(KeyringStore),
+        Err(probe_err) => match EncryptedFileStore::for_app(app) {
+            Ok(file_store) => {
+                log::warn!(
+                    "OS keychain unavailable ({}); storing secrets in {}",
+                    probe_err,
+                    file_store
+                        .path()
+                        .map_or_else(|err| err, |path| path.display().to_string())
+                );
+                Box::new(file_store)
+            }
+            Err(err) => {
+                log::error!(
+                    "OS keychain unavailable ({}) and the encrypted file store could not be set up: {}",
+                    probe_err,
+                    err
+                );
+                Box::new(KeyringStore)
+            }
+        },
+    };
+    log::info!("Secret storage backend: {}", backend.name());
+    if STORE.set(backend).is_err() {
+        log::warn!("Secret storage backend was already initialized");
+    }
+}
+
+#[derive(Serialize)]
+pub struct BackendInfo {
+    /// `"keychain"`, `"secret-service"`, `"wincred"` or `"encrypted-file"`.
+    pub name: &'static str,
+    pub os_backed: bool,
+    pub can_enumerate: bool,
+    /// Set when the OS keychain failed the startup probe, explaining why
+    /// another backend (or a keychain that may not work) is in use.
+    pub keychain_error: Option<String>,
+}
+
+/// Where secrets actually live, as selected by the probe in [`init_backend`],
+/// so the UI can tell the user.
+#[tauri::command]
+pub fn secret_backend_info() -> Result<BackendInfo, CommandError> {
+    let store = store();
+    let name = if store.os_backed() {
+        KeyringStore::platform_name()
+    } else {
+        store.name()
+    };
+    Ok(BackendInfo {
+        name,
+        os_backed: store.os_backed(),
+        can_enumerate: store.can_enumerate(),
+        keychain_error: KEYCHAIN_PROBE_ERROR.get().cloned(),
+    })
+}
+
+#[derive(Serialize)]
+pub struct CompactResult {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Rewrites the encrypted-file store with only its live entries under a new
+/// data key and a freshly salted machine key. Fails as not applicable when
+/// secrets are in the OS keychain.
+#[tauri::command]
+pub fn compact_secret_store() -> Result<CompactResult, CommandError> {
+    let _guard = write_guard();
+    let (bytes_before, bytes_after) = store().compact()?;
+    log::info!(
+        "Compacted secret store: {} -> {} bytes",
+        bytes_before,
+        bytes_after
+    );
+    Ok(CompactResult {
+        bytes_before,
+        bytes_after,
+    })
+}
+
+/// Reserved key holding a JSON array with the names of every key stored for a
+/// service. `keyring` can't enumerate entries on every platform, so
+/// `set_secret`/`delete_secret` keep this index in sync instead.
+const INDEX_KEY: &str = "__index__";
+
+/// Suffix of the companion entry holding a secret's [`SecretMeta`] as JSON.
+const META_SUFFIX: &str = ":__meta__";
+
+/// Serializes every mutation so read-modify-write sequences (index updates,
+/// rotation) can't interleave across concurrent IPC calls.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+fn write_guard() -> MutexGuard<'static, ()> {
+    WRITE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Emitted after a secret is written or deleted so other windows can drop
+/// cached values. Never carries the value itself.
+const SECRET_CHANGED_EVENT: &str = "secret-changed";
+
+#[derive(Clone, Serialize)]
+struct SecretChanged<'a> {
+    service: &'a str,
+    key: &'a str,
+    profile: Option<&'a str>,
+}
+
+fn notify_changed(app: &AppHandle, service: &str, key: &str, profile: Option<&str>) {
+    let payload = SecretChanged {
+        service,
+        key,
+        profile,
+    };
+    if let Err(err) = app.emit(SECRET_CHANGED_EVENT, payload) {
+        log::warn!("Failed to emit {}: {}", SECRET_CHANGED_EVENT, err);
+    }
+}
+
+fn ensure_not_reserved(key: &str) -> Result<(), CommandError> {
+    // A bare `__meta__` would collide with the meta entry of a key named
+    // after a profile (`profile` + `:__meta__`).
+    if key == INDEX_KEY || key == &META_SUFFIX[1..] || key.ends_with(META_SUFFIX) {
+        return Err(CommandError::InvalidInput(format!(
+            "Key '{}' is reserved",
+            key
+        )));
+    }
+    Ok(())
+}
+
+fn validate_profile(profile: &str) -> Result<(), CommandError> {
+    if profile.is_empty() || profile.contains(':') {
+        return Err(CommandError::InvalidInput(format!(
+            "Invalid profile '{}': must be non-empty and must not contain ':'",
+            profile
+        )));
+    }
+    Ok(())
+}
+
+/// Keyring username for `key`. Profiles are folded in as `profile:key`;
+/// without a profile the key is used as-is so existing entries keep resolving.
+fn account(key: &str, profile: Option<&str>) -> Result<String, CommandError> {
+    match profile {
+        None => Ok(key.to_string()),
+        Some(profile) => {
+            validate_profile(profile)?;
+            Ok(format!("{}:{}", profile, key))
+        }
+    }
+}
+
+fn read_value(service: &str, key: &str) -> Result<Option<String>, CommandError> {
+    store().get(service, key)
+}
+
+fn write_value(service: &str, key: &str, value: &str) -> Result<(), CommandError> {
+    store().set(service, key, value)
+}
+
+fn delete_value(service: &str, key: &str) -> Result<(), CommandError> {
+    store().delete(service, key)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SecretMeta {
+    pub created_ms: i64,
+    pub updated_ms: i64,
+}
+
+fn meta_account(account: &str) -> String {
+    format!("{}{}", account, META_SUFFIX)
+}
+
+fn read_meta(service: &str, account: &str) -> Result<Option<SecretMeta>, CommandError> {
+    match read_value(service, &meta_account(account))? {
+        Some(raw) => Ok(serde_json::from_str(&raw)?),
+        None => Ok(None),
+    }
+}
+
+/// Records that `account` was just written. Metadata is advisory, so a
+/// failure here is logged rather than failing a write that already happened.
+fn touch_meta(service: &str, account: &str) {
+    let now = chrono::Utc::now().timestamp_millis();
+    let created_ms = match read_meta(service, account) {
+        Ok(Some(meta)) => meta.created_ms,
+        _ => now,
+    };
+    let meta = SecretMeta {
+        created_ms,
+        updated_ms: now,
+    };
+    let result = serde_json::to_string(&meta)
+        .map_err(CommandError::from)
+        .and_then(|raw| write_value(service, &meta_account(account), &raw));
+    if let Err(err) = result {
+        log::warn!("Failed to record secret metadata: {}", err);
+    }
+}
+
+fn delete_meta(service: &str, account: &str) {
+    if let Err(err) = delete_value(service, &meta_account(account)) {
+        log::warn!("Failed to delete secret metadata: {}", err);
+    }
+}
+
+/// Writes a secret value and stamps its metadata.
+fn write_secret(service: &str, account: &str, value: &str) -> Result<(), CommandError> {
+    write_value(service, account, value)?;
+    touch_meta(service, account);
+    Ok(())
+}
+
+/// Deletes a secret value along with its metadata.
+fn remove_secret(service: &str, account: &str) -> Result<(), CommandError> {
+    delete_value(service, account)?;
+    delete_meta(service, account);
+    Ok(())
+}
+
+fn read_index(service: &str, profile: Option<&str>) -> Result<Vec<String>, CommandError> {
+    match read_value(service, &account(INDEX_KEY, profile)?)? {
+        Some(raw) => Ok(serde_json::from_str(&raw)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn write_index(service: &str, profile: Option<&str>, keys: &[String]) -> Result<(), CommandError> {
+    let index_account = account(INDEX_KEY, profile)?;
+    if keys.is_empty() {
+        return delete_value(service, &index_account);
+    }
+    let raw = serde_json::to_string(keys)?;
+    write_value(service, &index_account, &raw)
+}
+
+fn add_to_index<'a>(
+    service: &str,
+    profile: Option<&str>,
+    new_keys: impl IntoIterator<Item = &'a str>,
+) -> Result<(), CommandError> {
+    let mut keys = read_index(service, profile)?;
+    let mut changed = false;
+    for key in new_keys {
+        if let Err(pos) = keys.binary_search_by(|k| k.as_str().cmp(key)) {
+            keys.insert(pos, key.to_string());
+            changed = true;
+        }
+    }
+    if changed {
+        write_index(service, profile, &keys)?;
+    }
+    Ok(())
+}
+
+fn remove_from_index(service: &str, profile: Option<&str>, key: &str) -> Result<(), CommandError> {
+    let mut keys = read_index(service, profile)?;
+    if let Ok(pos) = keys.binary_search_by(|k| k.as_str().cmp(key)) {
+        keys.remove(pos);
+        write_index(service, profile, &keys)?;
+    }
+    Ok(())
+}
+
+// Every command takes an optional `profile` so several users sharing one OS
+// account don't collide on the same keychain entries.
+
+#[tauri::command]
+pub fn set_secret(
+    app: AppHandle,
+    service: String,
+    key: String,
+    value: String,
+    profile: Option<String>,
+) -> Result<(), CommandError> {
+    ensure_not_reserved(&key)?;
+    let profile = profile.as_deref();
+    let _guard = write_guard();
+    write_secret(&service, &account(&key, profile)?, &value)?;
+    add_to_index(&service, profile, [key.as_str()])?;
+    notify_changed(&app, &service, &key, profile);
+    Ok(())
+}
+
+/// Binary secrets are returned as their base64 text, without the tag.
+#[tauri::command]
+pub fn get_secret(
+    service: String,
+    key: String,
+    profile: Option<String>,
+) -> Result<Option<String>, CommandError> {
+    ensure_not_reserved(&key)?;
+    let value = read_value(&service, &account(&key, profile.as_deref())?)?;
+    Ok(value.map(|value| match value.strip_prefix(BINARY_TAG) {
+        Some(encoded) => encoded.to_string(),
+        None => value,
+    }))
+}
+
+/// Prefix marking a value stored by `set_secret_bytes`, so it isn't mistaken
+/// for a text secret that happens to be valid base64.
+const BINARY_TAG: &str = "jd-b64:";
+
+/// Stores binary data (client certificates, key files) as tagged base64,
+/// since most keychains only hold strings.
+///
+/// Size limits come from the platform: Windows Credential Manager caps a
+/// credential at 2560 bytes, stored as UTF-16, so binary secrets there must
+/// stay under roughly 900 bytes once base64 grows them by a third. The macOS
+/// keychain and Secret Service have no practical limit, but large items make
+/// every unlock slower; keep big key files on disk and store their passphrase
+/// here instead.
+#[tauri::command]
+pub fn set_secret_bytes(
+    app: AppHandle,
+    service: String,
+    key: String,
+    value: Vec<u8>,
+    profile: Option<String>,
+) -> Result<(), CommandError> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(value);
+    set_secret(
+        app,
+        service,
+        key,
+        format!("{}{}", BINARY_TAG, encoded),
+        profile,
+    )
+}
+
+/// Reads a secret stored by `set_secret_bytes`. Text secrets are rejected
+/// rather than guessed at.
+#[tauri::command]
+pub fn get_secret_bytes(
+    service: String,
+    key: String,
+    profile: Option<String>,
+) -> Result<Option<Vec<u8>>, CommandError> {
+    ensure_not_reserved(&key)?;
+    let Some(value) = read_value(&service, &account(&key, profile.as_deref())?)? else {
+        return Ok(None);
+    };
+    let encoded = value.strip_prefix(BINARY_TAG).ok_or_else(|| {
+        CommandError::InvalidInput(format!("Secret '{}' is not a binary secret", key))
+    })?;
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map(Some)
+        .map_err(|err| {
+            CommandError::Backend(format!("Secret '{}' holds invalid base64: {}", key, err))
+        })
+}
+
+#[tauri::command]
+pub fn delete_secret(
+    app: AppHandle,
+    service: String,
+    key: String,
+    profile: Option<String>,
+) -> Result<(), CommandError> {
+    ensure_not_reserved(&key)?;
+    let profile = profile.as_deref();
+    let _guard = write_guard();
+    remove_secret(&service, &account(&key, profile)?)?;
+    remove_from_index(&service, profile, &key)?;
+    notify_changed(&app, &service, &key, profile);
+    Ok(())
+}
+
+/// When a secret was first and last written; `None` for secrets written
+/// before metadata was recorded. Never returns the value.
+#[tauri::command]
+pub fn get_secret_metadata(
+    service: String,
+    key: String,
+    profile: Option<String>,
+) -> Result<Option<SecretMeta>, CommandError> {
+    ensure_not_reserved(&key)?;
+    read_meta(&service, &account(&key, profile.as_deref())?)
+}
+
+/// Returns the names (never the values) of every key stored for `service`.
+/// Secrets written before the index existed are not listed until they are
+/// written again.
+#[tauri::command]
+pub fn list_secrets(service: String, profile: Option<String>) -> Result<Vec<String>, CommandError> {
+    read_index(&service, profile.as_deref())
+}
+
+/// Outcome of a `set_secrets` batch that failed partway through.
+struct BatchWriteError {
+    failed_key: String,
+    error: String,
+    rolled_back: Vec<String>,
+    rollback_failures: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for BatchWriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to write key '{}': {}",
+            self.failed_key, self.error
+        )?;
+        write!(f, "; rolled back {} key(s)", self.rolled_back.len())?;
+        if !self.rolled_back.is_empty() {
+            write!(f, " [{}]", self.rolled_back.join(", "))?;
+        }
+        for (key, err) in &self.rollback_failures {
+            write!(f, "; rollback failed for '{}': {}", key, err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes every entry in order. If a write fails, the keys already written
+/// are restored to their previous value (or deleted if they didn't exist).
+/// The rollback is best-effort: the keychain has no transactions, so a
+/// rollback step can fail too, and the error reports which keys were restored
+/// and which could not be.
+#[tauri::command]
+pub fn set_secrets(
+    app: AppHandle,
+    service: String,
+    entries: Vec<(String, String)>,
+    profile: Option<String>,
+) -> Result<(), CommandError> {
+    let profile = profile.as_deref();
+    let mut accounts = Vec::with_capacity(entries.len());
+    for (key, _) in &entries {
+        ensure_not_reserved(key)?;
+        accounts.push(account(key, profile)?);
+    }
+    let _guard = write_guard();
+
+    let mut written: Vec<(&str, &str, Option<String>)> = Vec::new();
+    for ((key, value), account) in entries.iter().zip(&accounts) {
+        let result = read_value(&service, account)
+            .and_then(|previous| write_value(&service, account, value).map(|_| previous));
+        match result {
+            Ok(previous) => written.push((key, account, previous)),
+            Err(error) => {
+                let mut failure = BatchWriteError {
+                    failed_key: key.clone(),
+                    error: error.to_string(),
+                    rolled_back: Vec::new(),
+                    rollback_failures: Vec::new(),
+                };
+                for (key, account, previous) in written.into_iter().rev() {
+                    let restored = match previous {
+                        Some(previous) => write_value(&service, account, &previous),
+                        None => delete_value(&service, account),
+                    };
+                    match restored {
+                        Ok(()) => failure.rolled_back.push(key.to_string()),
+                        Err(err) => failure
+                            .rollback_failures
+                            .push((key.to_string(), err.to_string())),
+                    }
+                }
+                return Err(CommandError::Backend(failure.to_string()));
+            }
+        }
+    }
+
+    for account in &accounts {
+        touch_meta(&service, account);
+    }
+    add_to_index(
+        &service,
+        profile,
+        entries.iter().map(|(key, _)| key.as_str()),
+    )?;
+    for (key, _) in &entries {
+        notify_changed(&app, &service, key, profile);
+    }
+    Ok(())
+}
+
+/// Reports whether a secret exists without sending its value to the webview.
+/// A missing entry is `false`; keychain failures (e.g. a locked keychain) are
+/// returned as errors.
+#[tauri::command]
+pub fn has_secret(
+    service: String,
+    key: String,
+    profile: Option<String>,
+) -> Result<bool, CommandError> {
+    ensure_not_reserved(&key)?;
+    Ok(read_value(&service, &account(&key, profile.as_deref())?)?.is_some())
+}
+
+/// Copies a secret to the clipboard and clears it again after
+/// `clear_after_ms`, unless the clipboard has changed by then (so something
+/// the user copied afterwards survives). Binary secrets are refused; there is
+/// no text form of them worth pasting.
+#[tauri::command]
+pub fn copy_secret_to_clipboard(
+    app: AppHandle,
+    service: String,
+    key: String,
+    clear_after_ms: u64,
+    profile: Option<String>,
+) -> Result<(), CommandError> {
+    ensure_not_reserved(&key)?;
+    let value = read_value(&service, &account(&key, profile.as_deref())?)?
+        .ok_or_else(|| CommandError::NotFound(format!("Secret '{}' not found", key)))?;
+    if value.starts_with(BINARY_TAG) {
+        return Err(CommandError::InvalidInput(format!(
+            "Secret '{}' is binary and can't be copied as text",
+            key
+        )));
+    }
+    app.clipboard()
+        .write_text(value.as_str())
+        .map_err(|err| CommandError::Backend(err.to_string()))?;
+
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(clear_after_ms)).await;
+        let clipboard = app.clipboard();
+        if clipboard.read_text().ok().as_deref() != Some(value.as_str()) {
+            return;
+        }
+        if let Err(err) = clipboard.clear() {
+            log::warn!("Failed to clear copied secret from the clipboard: {}", err);
+        }
+    });
+    Ok(())
+}
+
+/// Replaces the value of `key` and returns the previous one (`None` if there
+/// was none) so the caller can revoke it downstream. A failed write leaves the
+/// old value in place.
+#[tauri::command]
+pub fn rotate_secret(
+    app: AppHandle,
+    service: String,
+    key: String,
+    new_value: String,
+    profile: Option<String>,
+) -> Result<Option<String>, CommandError> {
+    ensure_not_reserved(&key)?;
+    let profile = profile.as_deref();
+    let account = account(&key, profile)?;
+    let _guard = write_guard();
+    let previous = read_value(&service, &account)?;
+    write_secret(&service, &account, &new_value)?;
+    add_to_index(&service, profile, [key.as_str()])?;
+    notify_changed(&app, &service, &key, profile);
+    Ok(previous)
+}
+
+/// Magic bytes of the passphrase-encrypted `{key: value}` JSON bundles used to
+/// move secrets between machines.
+const BUNDLE_MAGIC: &[u8; 4] = b"JDSB";
+
+/// Writes every indexed key of `service` into a bundle for `import_secrets`
+/// and returns how many were exported. The bundle uses the `crypto` file
+/// format (version byte and Argon2 salt in the header) and is encrypted in
+/// memory, so the plaintext never touches disk.
+#[tauri::command]
+pub fn export_secrets(
+    app: AppHandle,
+    service: String,
+    dest_path: String,
+    passphrase: String,
+    profile: Option<String>,
+) -> Result<u32, CommandError> {
+    let profile = profile.as_deref();
+    let dest_path = resolve_save_path(&app, &dest_path).map_err(CommandError::InvalidInput)?;
+
+    let mut entries = BTreeMap::new();
+    for key in read_index(&service, profile)? {
+        if let Some(value) = read_value(&service, &account(&key, profile)?)? {
+            entries.insert(key, value);
+        }
+    }
+    let plaintext = serde_json::to_vec(&entries)?;
+    let sealed =
+        crypto::seal(BUNDLE_MAGIC, &passphrase, &plaintext).map_err(CommandError::InvalidInput)?;
+    fs::write(&dest_path, sealed)?;
+    Ok(entries.len() as u32)
+}
+
+/// Writes every key from a bundle made by `export_secrets` and returns how
+/// many were written. The bundle has no timestamps to tell which copy is
+/// newer, so keys that already exist are skipped unless `overwrite` is set.
+/// A wrong passphrase and a damaged file fail with different errors.
+#[tauri::command]
+pub fn import_secrets(
+    app: AppHandle,
+    service: String,
+    bundle_path: String,
+    passphrase: String,
+    overwrite: bool,
+    profile: Option<String>,
+) -> Result<u32, CommandError> {
+    let profile = profile.as_deref();
+    let bundle_path = resolve_app_path(&app, &bundle_path).map_err(CommandError::InvalidInput)?;
+    let data = fs::read(&bundle_path)?;
+    let plaintext = crypto::open(BUNDLE_MAGIC, &passphrase, &data)?;
+    let entries: BTreeMap<String, String> = serde_json::from_slice(&plaintext)?;
+    for key in entries.keys() {
+        ensure_not_reserved(key)?;
+    }
+
+    let _guard = write_guard();
+    let mut imported = 0;
+    for (key, value) in &entries {
+        let account = account(key, profile)?;
+        let existed = read_value(&service, &account)?.is_some();
+        if existed && !overwrite {
+            continue;
+        }
+        // Indexed one by one, so a failure further on can't leave secrets
+        // that `list_secrets` and `clear_all_secrets` don't know about.
+        write_secret(&service, &account, value)?;
+        if let Err(err) = add_to_index(&service, profile, [key.as_str()]) {
+            if existed {
+                return Err(err);
+            }
+            if let Err(remove_err) = remove_secret(&service, &account) {
+                log::warn!("Failed to roll back import of '{}': {}", key, remove_err);
+            }
+            return Err(err);
+        }
+        notify_changed(&app, &service, key, profile);
+        imported += 1;
+    }
+    Ok(imported)
+}
+
+/// Value `clear_all_secrets` requires as `confirm`.
+const CLEAR_ALL_CONFIRMATION: &str = "DELETE ALL SECRETS";
+
+/// Deletes every indexed secret for `service` (e.g. on logout from a shared
+/// machine) and returns how many were removed. `confirm` must equal
+/// `"DELETE ALL SECRETS"` so a stray IPC call can't wipe credentials. A key
+/// that fails to delete doesn't stop the rest; the failures are reported
+/// together and those keys stay in the index.
+#[tauri::command]
+pub fn clear_all_secrets(
+    app: AppHandle,
+    service: String,
+    confirm: String,
+    profile: Option<String>,
+) -> Result<u32, CommandError> {
+    if confirm != CLEAR_ALL_CONFIRMATION {
+        return Err(CommandError::InvalidInput(format!(
+            "Confirmation must be exactly '{}'",
+            CLEAR_ALL_CONFIRMATION
+        )));
+    }
+    let profile = profile.as_deref();
+    let _guard = write_guard();
+    let keys = read_index(&service, profile)?;
+
+    let mut removed = 0;
+    let mut remaining = Vec::new();
+    let mut failures = Vec::new();
+    for key in keys {
+        match remove_secret(&service, &account(&key, profile)?) {
+            Ok(()) => {
+                notify_changed(&app, &service, &key, profile);
+                removed += 1;
+            }
+            Err(err) => {
+                failures.push(format!("'{}': {}", key, err));
+                remaining.push(key);
+            }
+        }
+    }
+    write_index(&service, profile, &remaining)?;
+
+    if failures.is_empty() {
+        return Ok(removed);
+    }
+    Err(CommandError::Backend(format!(
+        "Deleted {} secret(s) but failed to delete {}: {}",
+        removed,
+        failures.len(),
+        failures.join("; ")
+    )))
+}
+
+/// Moves un-profiled secrets into `profile` and returns how many were moved.
+/// Defaults to every key in the un-profiled index; pass `keys` explicitly for
+/// secrets stored before the index existed. Keys that already exist in the
+/// profile are left untouched (in both places) rather than overwritten.
+#[tauri::command]
+pub fn migrate_secrets_to_profile(
+    app: AppHandle,
+    service: String,
+    profile: String,
+    keys: Option<Vec<String>>,
+) -> Result<u32, CommandError> {
+    validate_profile(&profile)?;
+    let _guard = write_guard();
+    let keys = match keys {
+        Some(keys) => keys,
+        None => read_index(&service, None)?,
+    };
+
+    let mut moved = 0;
+    for key in &keys {
+        ensure_not_reserved(key)?;
+        let target = account(key, Some(&profile))?;
+        let Some(value) = read_value(&service, key)? else {
+            continue;
+        };
+        if read_value(&service, &target)?.is_some() {
+            continue;
+        }
+        write_value(&service, &target, &value)?;
+        match read_meta(&service, key) {
+            Ok(Some(meta)) => {
+                let raw = serde_json::to_string(&meta)?;
+                write_value(&service, &meta_account(&target), &raw)?;
+            }
+            Ok(None) => {}
+            Err(err) => log::warn!("Failed to read secret metadata: {}", err),
+        }
+        add_to_index(&service, Some(&profile), [key.as_str()])?;
+        remove_secret(&service, key)?;
+        remove_from_index(&service, None, key)?;
+        notify_changed(&app, &service, key, None);
+        notify_changed(&app, &service, key, Some(&profile));
+        moved += 1;
+    }
+    Ok(moved)
+}
@@ -0,0 +1,421 @@
+//! Backends the secret commands read and write through.
+//!
+//! The OS keychain is preferred. On machines without one (e.g. Linux without
+//! a Secret Service daemon) secrets go to an encrypted file in the app data
+//! directory instead, so the app keeps working rather than failing every
+//! credential lookup.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::AppHandle;
+
+use crate::crypto::{self, KEY_LEN, SALT_LEN};
+use crate::error::CommandError;
+
+pub trait SecretStore: Send + Sync {
+    /// Short identifier used in logs and diagnostics.
+    fn name(&self) -> &'static str;
+    /// Whether secrets are held by the OS (keychain, credential manager)
+    /// rather than by the app.
+    fn os_backed(&self) -> bool;
+    /// Whether the store itself can list its entries. Where it can't, the
+    /// secret commands rely on their own index.
+    fn can_enumerate(&self) -> bool;
+    /// `Ok(None)` when the entry doesn't exist.
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, CommandError>;
+    fn set(&self, service: &str, account: &str, value: &str) -> Result<(), CommandError>;
+    /// Deleting a missing entry is not an error.
+    fn delete(&self, service: &str, account: &str) -> Result<(), CommandError>;
+    /// Drops any keys or decrypted values held in memory; the next access
+    /// loads them again.
+    fn forget_cached(&self) {}
+    /// Rewrites the store's file under fresh keys and returns its size
+    /// before and after. Only file-backed stores support this.
+    fn compact(&self) -> Result<(u64, u64), CommandError> {
+        Err(CommandError::InvalidInput(format!(
+            "Not applicable: secrets are held by the {} backend, not the encrypted file",
+            self.name()
+        )))
+    }
+}
+
+pub struct KeyringStore;
+
+const PROBE_SERVICE: &str = "jurisdesk";
+const PROBE_ACCOUNT: &str = "__probe__";
+
+impl KeyringStore {
+    /// The OS facility `keyring` talks to on this platform.
+    pub fn platform_name() -> &'static str {
+        if cfg!(target_os = "macos") {
+            "keychain"
+        } else if cfg!(target_os = "windows") {
+            "wincred"
+        } else {
+            "secret-service"
+        }
+    }
+
+    /// Stores a random value in a reserved entry, reads it back through a
+    /// second handle and deletes it. A lookup alone proves nothing: keyring's
+    /// in-memory mock (used when no platform store is compiled in) and a
+    /// locked keychain both answer it, yet would lose or refuse secrets.
+    /// Runs once at startup without retries, so a missing keychain doesn't
+    /// delay the launch.
+    pub fn probe() -> Result<(), CommandError> {
+        let token: String = crypto::random_bytes::<16>()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        entry(PROBE_SERVICE, PROBE_ACCOUNT)?.set_password(&token)?;
+        let read_back = entry(PROBE_SERVICE, PROBE_ACCOUNT)?.get_password();
+        if let Err(err) = entry(PROBE_SERVICE, PROBE_ACCOUNT)?.delete_credential() {
+            log::warn!("Failed to remove keychain probe entry: {}", err);
+        }
+        match read_back {
+            Ok(value) if value == token => Ok(()),
+            Ok(_) | Err(keyring::Error::NoEntry) => Err(CommandError::Backend(
+                "Keychain did not return the value just stored in it".to_string(),
+            )),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+fn entry(service: &str, account: &str) -> Result<keyring::Entry, CommandError> {
+    Ok(keyring::Entry::new(service, account)?)
+}
+
+/// Pauses before each retry of a keychain call; the list length bounds the
+/// retries and its sum (under a second) the extra time the UI can wait.
+const RETRY_BACKOFF: [Duration; 2] = [Duration::from_millis(100), Duration::from_millis(400)];
+
+/// Failures that can clear up on their own, e.g. the keychain being
+/// momentarily locked while the user logs in. `NoEntry` is a definitive
+/// answer, and bad input fails the same way every time.
+fn is_transient(err: &keyring::Error) -> bool {
+    matches!(
+        err,
+        keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_)
+    )
+}
+
+/// Runs a keychain call, retrying transient failures with backoff.
+fn with_retry<T>(
+    operation: &str,
+    mut call: impl FnMut() -> keyring::Result<T>,
+) -> keyring::Result<T> {
+    let mut backoff = RETRY_BACKOFF.iter();
+    loop {
+        match call() {
+            Err(err) if is_transient(&err) => match backoff.next() {
+                Some(delay) => {
+                    log::debug!(
+                        "Keychain {} failed ({}); retrying in {:?}",
+                        operation,
+                        err,
+                        delay
+                    );
+                    std::thread::sleep(*delay);
+                }
+                None => return Err(err),
+            },
+            result => return result,
+        }
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn os_backed(&self) -> bool {
+        true
+    }
+
+    fn can_enumerate(&self) -> bool {
+        false
+    }
+
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, CommandError> {
+        let entry = entry(service, account)?;
+        match with_retry("read", || entry.get_password()) {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn set(&self, service: &str, account: &str, value: &str) -> Result<(), CommandError> {
+        let entry = entry(service, account)?;
+        Ok(with_retry("write", || entry.set_password(value))?)
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), CommandError> {
+        let entry = entry(service, account)?;
+        match with_retry("delete", || entry.delete_credential()) {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+// File layout: `magic (4) | version (1) | kek salt (16) | wrapped dek | data`.
+// The data key (DEK) is random and encrypts the JSON entries; it is itself
+// encrypted ("wrapped") under a key derived from the machine id. Both
+// ciphertexts are `nonce | ciphertext` and authenticate the bytes before them.
+const FILE_MAGIC: &[u8; 4] = b"JDSS";
+const FILE_VERSION: u8 = 1;
+const FILE_NAME: &str = "secrets.enc";
+const PREFIX_LEN: usize = 4 + 1 + SALT_LEN;
+const WRAPPED_DEK_LEN: usize = 12 + KEY_LEN + 16;
+
+/// service -> account -> value
+type Entries = BTreeMap<String, BTreeMap<String, String>>;
+
+struct FileState {
+    kek_salt: [u8; SALT_LEN],
+    kek: [u8; KEY_LEN],
+    dek: [u8; KEY_LEN],
+    entries: Entries,
+}
+
+/// Secrets kept in one encrypted file, rewritten atomically on every change.
+/// The key is bound to this machine, so a copied file is useless elsewhere;
+/// it does not protect against other processes running as the same user,
+/// which the OS keychain would.
+pub struct EncryptedFileStore {
+    app: AppHandle,
+    machine_secret: Vec<u8>,
+    state: Mutex<Option<FileState>>,
+}
+
+impl EncryptedFileStore {
+    pub fn for_app(app: &AppHandle) -> Result<Self, String> {
+        let machine_id =
+            machine_uid::get().map_err(|err| format!("Failed to read the machine id: {}", err))?;
+        let secret = format!("{}:{}", app.config().identifier, machine_id.trim());
+        Ok(Self {
+            app: app.clone(),
+            machine_secret: secret.into_bytes(),
+            state: Mutex::new(None),
+        })
+    }
+
+    /// Follows the data dir, so the file is found again after
+    /// `relocate_data_directory` moves it.
+    pub fn path(&self) -> Result<PathBuf, String> {
+        crate::paths::data_dir(&self.app).map(|dir| dir.join(FILE_NAME))
+    }
+
+    /// Runs `f` against the decrypted entries, loading the file on first use.
+    /// A file that exists but can't be decrypted is an error on every call and
+    /// is never overwritten, so a changed machine id doesn't wipe secrets.
+    fn with_state<T>(
+        &self,
+        f: impl FnOnce(&mut FileState) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut guard = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if guard.is_none() {
+            *guard = Some(self.load()?);
+        }
+        f(guard.as_mut().expect("state was just loaded"))
+    }
+
+    fn load(&self) -> Result<FileState, String> {
+        let path = self.path()?;
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let kek_salt = crypto::random_bytes();
+                return Ok(FileState {
+                    kek: crypto::derive_key(&self.machine_secret, &kek_salt)?,
+                    kek_salt,
+                    dek: crypto::random_bytes(),
+                    entries: Entries::new(),
+                });
+            }
+            Err(err) => return Err(err.to_string()),
+        };
+
+        let corrupt =
+            |reason: &str| format!("Secret file {} is unreadable: {}", path.display(), reason);
+        if data.len() < PREFIX_LEN + WRAPPED_DEK_LEN || &data[..4] != FILE_MAGIC {
+            return Err(corrupt("unrecognized format"));
+        }
+        if data[4] != FILE_VERSION {
+            return Err(corrupt(&format!("unsupported version {}", data[4])));
+        }
+        let mut kek_salt = [0u8; SALT_LEN];
+        kek_salt.copy_from_slice(&data[5..PREFIX_LEN]);
+        let kek = crypto::derive_key(&self.machine_secret, &kek_salt)?;
+
+        let header_len = PREFIX_LEN + WRAPPED_DEK_LEN;
+        let dek = crypto::decrypt(&kek, &data[PREFIX_LEN..header_len], &data[..PREFIX_LEN])
+            .map_err(|err| corrupt(&err))?;
+        let dek: [u8; KEY_LEN] = dek
+            .try_into()
+            .map_err(|_| corrupt("data key has the wrong length"))?;
+        let plaintext = crypto::decrypt(&dek, &data[header_len..], &data[..header_len])
+            .map_err(|err| corrupt(&err))?;
+        let entries =
+            serde_json::from_slice(&plaintext).map_err(|err| corrupt(&err.to_string()))?;
+
+        Ok(FileState {
+            kek_salt,
+            kek,
+            dek,
+            entries,
+        })
+    }
+
+    fn save(&self, state: &FileState) -> Result<(), String> {
+        let mut data = Vec::new();
+        data.extend_from_slice(FILE_MAGIC);
+        data.push(FILE_VERSION);
+        data.extend_from_slice(&state.kek_salt);
+        let wrapped = crypto::encrypt(&state.kek, &state.dek, &data)?;
+        data.extend_from_slice(&wrapped);
+        let plaintext = serde_json::to_vec(&state.entries).map_err(|err| err.to_string())?;
+        let sealed = crypto::encrypt(&state.dek, &plaintext, &data)?;
+        data.extend_from_slice(&sealed);
+
+        let path = self.path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let tmp = path.with_extension("enc.tmp");
+        write_private(&tmp, &data).map_err(|err| err.to_string())?;
+        fs::rename(&tmp, &path).map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            err.to_string()
+        })
+    }
+}
+
+/// Writes `data` to a file only the current user can read.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn name(&self) -> &'static str {
+        "encrypted-file"
+    }
+
+    fn os_backed(&self) -> bool {
+        false
+    }
+
+    fn can_enumerate(&self) -> bool {
+        true
+    }
+
+    fn get(&self, service: &str, account: &str) -> Result<Option<String>, CommandError> {
+        self.with_state(|state| {
+            Ok(state
+                .entries
+                .get(service)
+                .and_then(|accounts| accounts.get(account))
+                .cloned())
+        })
+        .map_err(CommandError::Backend)
+    }
+
+    fn set(&self, service: &str, account: &str, value: &str) -> Result<(), CommandError> {
+        self.with_state(|state| {
+            let previous = state
+                .entries
+                .entry(service.to_string())
+                .or_default()
+                .insert(account.to_string(), value.to_string());
+            self.save(state).inspect_err(|_| {
+                // Keep memory in sync with what's on disk.
+                let accounts = state.entries.entry(service.to_string()).or_default();
+                match previous {
+                    Some(previous) => accounts.insert(account.to_string(), previous),
+                    None => accounts.remove(account),
+                };
+            })
+        })
+        .map_err(CommandError::Backend)
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), CommandError> {
+        self.with_state(|state| {
+            let Some(accounts) = state.entries.get_mut(service) else {
+                return Ok(());
+            };
+            let Some(previous) = accounts.remove(account) else {
+                return Ok(());
+            };
+            if accounts.is_empty() {
+                state.entries.remove(service);
+            }
+            self.save(state).inspect_err(|_| {
+                state
+                    .entries
+                    .entry(service.to_string())
+                    .or_default()
+                    .insert(account.to_string(), previous);
+            })
+        })
+        .map_err(CommandError::Backend)
+    }
+
+    fn compact(&self) -> Result<(u64, u64), CommandError> {
+        let path = self.path().map_err(CommandError::Backend)?;
+        let file_len = || fs::metadata(&path).map_or(0, |meta| meta.len());
+        self.with_state(|state| {
+            let before = file_len();
+            state.entries.retain(|_, accounts| !accounts.is_empty());
+            let kek_salt = crypto::random_bytes();
+            let fresh = FileState {
+                kek: crypto::derive_key(&self.machine_secret, &kek_salt)?,
+                kek_salt,
+                dek: crypto::random_bytes(),
+                entries: std::mem::take(&mut state.entries),
+            };
+            match self.save(&fresh) {
+                Ok(()) => *state = fresh,
+                // The old file is still in place; keep the keys that open it.
+                Err(err) => {
+                    state.entries = fresh.entries;
+                    return Err(err);
+                }
+            }
+            Ok((before, file_len()))
+        })
+        .map_err(CommandError::Backend)
+    }
+
+    fn forget_cached(&self) {
+        let mut guard = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(state) = guard.as_mut() {
+            state.kek.fill(0);
+            state.dek.fill(0);
+        }
+        *guard = None;
+    }
+}
@@ -0,0 +1,198 @@
+//! Rust-side preferences persisted as JSON in the app data dir. Secrets never
+//! go here; they live in the keychain (see `secrets`).
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::window_state::{self, WindowState};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub log_level: Option<String>,
+    pub backup_schedule: Option<BackupSchedule>,
+    /// Minutes without input before the app locks itself; 0 disables it.
+    pub idle_timeout_minutes: u32,
+    /// Free space (bytes) below which `disk_monitor` warns; `None` means the
+    /// default, 0 disables the warning.
+    pub low_disk_threshold_bytes: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BackupSchedule {
+    pub interval_hours: u32,
+    /// Absolute folder picked by the user, or relative to the app data dir.
+    pub dest_dir: String,
+    /// How many automatic backups to retain.
+    pub keep: u32,
+    #[serde(default)]
+    pub last_run_ms: Option<i64>,
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::paths::data_dir(app).map(|dir| dir.join(SETTINGS_FILE))
+}
+
+fn read(path: &Path) -> Settings {
+    match fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|err| {
+            log::warn!("Ignoring unreadable {}: {}", path.display(), err);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
+
+/// Current settings; a missing or unreadable file yields the defaults.
+pub fn load(app: &AppHandle) -> Settings {
+    match settings_path(app) {
+        Ok(path) => read(&path),
+        Err(_) => Settings::default(),
+    }
+}
+
+/// Applies `change` and writes the file atomically (temp file + rename).
+pub fn update(app: &AppHandle, change: impl FnOnce(&mut Settings)) -> Result<(), String> {
+    let path = settings_path(app)?;
+    let _guard = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut settings = read(&path);
+    change(&mut settings);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(&settings).map_err(|err| err.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, raw).map_err(|err| err.to_string())?;
+    fs::rename(&tmp, &path).map_err(|err| err.to_string())
+}
+
+/// What `export_settings_snapshot` captures. Fields are listed one by one
+/// rather than copying `Settings` wholesale, so nothing added to the app
+/// later (a token, say) can end up in a snapshot sent to support unnoticed.
+#[derive(Serialize)]
+struct SettingsSnapshot {
+    app_version: String,
+    /// Not compared by `diff_settings_snapshots`.
+    taken_at_ms: i64,
+    data_dir: Option<String>,
+    log_level: Option<String>,
+    backup_schedule: Option<BackupSchedule>,
+    idle_timeout_minutes: u32,
+    low_disk_threshold_bytes: Option<u64>,
+    window_state: Option<WindowState>,
+}
+
+/// Serializes the app's non-secret settings as pretty-printed JSON for a
+/// support ticket. Keychain contents are never included.
+#[tauri::command]
+pub fn export_settings_snapshot(app: AppHandle) -> Result<String, String> {
+    let settings = load(&app);
+    let snapshot = SettingsSnapshot {
+        app_version: app.package_info().version.to_string(),
+        taken_at_ms: chrono::Utc::now().timestamp_millis(),
+        data_dir: crate::paths::data_dir(&app)
+            .ok()
+            .map(|dir| dir.display().to_string()),
+        log_level: settings.log_level,
+        backup_schedule: settings.backup_schedule,
+        idle_timeout_minutes: settings.idle_timeout_minutes,
+        low_disk_threshold_bytes: settings.low_disk_threshold_bytes,
+        window_state: window_state::load(&app),
+    };
+    serde_json::to_string_pretty(&snapshot).map_err(|err| err.to_string())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize)]
+pub struct SettingChange {
+    /// Dotted path such as `backup_schedule.keep`.
+    pub key: String,
+    pub kind: ChangeKind,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Leaf values of a snapshot keyed by dotted path. An empty object or `null`
+/// is a leaf, so a setting being cleared shows up as a change.
+fn flatten(prefix: String, value: Value, leaves: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                let path = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(path, value, leaves);
+            }
+        }
+        value => {
+            leaves.insert(prefix, value);
+        }
+    }
+}
+
+fn parse_snapshot(raw: &str, name: &str) -> Result<BTreeMap<String, Value>, String> {
+    let value: Value = serde_json::from_str(raw)
+        .map_err(|err| format!("Snapshot {} is not valid JSON: {}", name, err))?;
+    if !value.is_object() {
+        return Err(format!("Snapshot {} is not a settings snapshot", name));
+    }
+    let mut leaves = BTreeMap::new();
+    flatten(String::new(), value, &mut leaves);
+    leaves.remove("taken_at_ms");
+    Ok(leaves)
+}
+
+/// Lists the settings that differ between two `export_settings_snapshot`
+/// outputs, `a` being the older one, sorted by key.
+#[tauri::command]
+pub fn diff_settings_snapshots(a: String, b: String) -> Result<Vec<SettingChange>, String> {
+    let mut before = parse_snapshot(&a, "a")?;
+    let after = parse_snapshot(&b, "b")?;
+    let mut changes = Vec::new();
+    for (key, new) in after {
+        match before.remove(&key) {
+            None => changes.push(SettingChange {
+                key,
+                kind: ChangeKind::Added,
+                before: None,
+                after: Some(new),
+            }),
+            Some(old) if old != new => changes.push(SettingChange {
+                key,
+                kind: ChangeKind::Changed,
+                before: Some(old),
+                after: Some(new),
+            }),
+            Some(_) => {}
+        }
+    }
+    changes.extend(before.into_iter().map(|(key, old)| SettingChange {
+        key,
+        kind: ChangeKind::Removed,
+        before: Some(old),
+        after: None,
+    }));
+    changes.sort_by(|x, y| x.key.cmp(&y.key));
+    Ok(changes)
+}
@@ -0,0 +1,180 @@
+//! Folder watching for the document inbox: files dropped into a watched
+//! directory are reported to the frontend as `directory-changed` events.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rand::RngCore;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::paths::resolve_app_path;
+
+const DIRECTORY_CHANGED_EVENT: &str = "directory-changed";
+/// Events for the same path closer together than this are reported once.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+/// Upper bound on how long a steady stream of events (e.g. a large copy) can
+/// hold back reporting.
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Serialize)]
+struct DirectoryChanged {
+    watch_id: String,
+    /// Relative to the app data dir, like the paths commands accept.
+    path: String,
+    kind: ChangeKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ChangeKind {
+    Created,
+    Modified,
+    Renamed,
+    Removed,
+}
+
+impl ChangeKind {
+    fn from_event(kind: &EventKind) -> Option<Self> {
+        match kind {
+            EventKind::Create(_) => Some(ChangeKind::Created),
+            EventKind::Modify(ModifyKind::Name(_)) => Some(ChangeKind::Renamed),
+            EventKind::Modify(_) => Some(ChangeKind::Modified),
+            EventKind::Remove(_) => Some(ChangeKind::Removed),
+            // Access events and the like don't change anything.
+            _ => None,
+        }
+    }
+
+    /// Folds a later event for the same path into an earlier one: a file
+    /// that was created and then written is still reported as created.
+    fn merge(self, later: Self) -> Self {
+        match (self, later) {
+            (ChangeKind::Created, ChangeKind::Modified) => ChangeKind::Created,
+            (_, later) => later,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Watchers(Mutex<HashMap<String, RecommendedWatcher>>);
+
+impl Watchers {
+    fn active(&self) -> MutexGuard<'_, HashMap<String, RecommendedWatcher>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Collects events until `DEBOUNCE` passes without a new one, then emits one
+/// event per path. Ends when the watcher (the sender) is dropped.
+fn debounce_loop(
+    app: AppHandle,
+    watch_id: String,
+    root: PathBuf,
+    relative_root: String,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+) {
+    let mut pending: HashMap<PathBuf, ChangeKind> = HashMap::new();
+    let mut first_pending: Option<Instant> = None;
+    loop {
+        let received = if pending.is_empty() {
+            events.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            events.recv_timeout(DEBOUNCE)
+        };
+        match received {
+            Ok(Ok(event)) => {
+                let Some(kind) = ChangeKind::from_event(&event.kind) else {
+                    continue;
+                };
+                for path in event.paths {
+                    pending
+                        .entry(path)
+                        .and_modify(|current| *current = current.merge(kind))
+                        .or_insert(kind);
+                }
+                let started = *first_pending.get_or_insert_with(Instant::now);
+                if started.elapsed() < MAX_DELAY {
+                    continue;
+                }
+            }
+            Ok(Err(err)) => {
+                log::warn!("Watcher {} error: {}", watch_id, err);
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        first_pending = None;
+        for (path, kind) in pending.drain() {
+            let payload = DirectoryChanged {
+                watch_id: watch_id.clone(),
+                path: relative_to(&root, &relative_root, &path),
+                kind,
+            };
+            if let Err(err) = app.emit(DIRECTORY_CHANGED_EVENT, payload) {
+                log::warn!("Failed to emit {}: {}", DIRECTORY_CHANGED_EVENT, err);
+            }
+        }
+    }
+}
+
+fn relative_to(root: &Path, relative_root: &str, path: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(rest) => Path::new(relative_root).join(rest).display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+/// Starts watching a directory under the app data dir (recursively) and
+/// returns an id for `unwatch_directory`.
+#[tauri::command]
+pub fn watch_directory(
+    app: AppHandle,
+    watchers: State<'_, Watchers>,
+    path: String,
+) -> Result<String, String> {
+    let root = resolve_app_path(&app, &path)?;
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let id: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|err| err.to_string())?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|err| err.to_string())?;
+
+    let thread_id = id.clone();
+    std::thread::Builder::new()
+        .name(format!("watch-{}", id))
+        .spawn(move || debounce_loop(app, thread_id, root, path, rx))
+        .map_err(|err| err.to_string())?;
+    watchers.active().insert(id.clone(), watcher);
+    Ok(id)
+}
+
+/// Stops a watch. Unknown ids are not an error.
+#[tauri::command]
+pub fn unwatch_directory(watchers: State<'_, Watchers>, id: String) {
+    watchers.active().remove(&id);
+}
+
+/// Drops every watcher; call on exit so no OS watch handles outlive the app.
+pub fn stop_all(app: &AppHandle) {
+    if let Some(watchers) = app.try_state::<Watchers>() {
+        watchers.active().clear();
+    }
+}
@@ -0,0 +1,210 @@
+//! Remembers the main window's size, position and maximized state across
+//! launches. The window is created hidden (see `tauri.conf.json`) and shown
+//! by [`restore`] once the saved geometry has been applied.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, Runtime, WebviewWindow, Window,
+    WindowEvent,
+};
+
+const STATE_FILE: &str = "window-state.json";
+const MAIN_WINDOW: &str = "main";
+/// Moves and resizes arrive many times a second while dragging; the state is
+/// written once they stop for this long.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+/// How much of the title bar must land on a monitor for a saved position to
+/// be used as is.
+const MIN_VISIBLE: i32 = 100;
+
+/// Bumped by every move/resize; a pending save only runs if it's still current.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Geometry in physical pixels. While maximized, the size and position are
+/// the ones from before maximizing, so un-maximizing after a restart works.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct WindowState {
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    maximized: bool,
+}
+
+/// The main window, or an error saying it doesn't exist (not created yet, or
+/// a context without windows) for callers to report instead of panicking.
+pub fn main_window<R: Runtime>(app: &AppHandle<R>) -> Result<WebviewWindow<R>, String> {
+    app.get_webview_window(MAIN_WINDOW)
+        .ok_or_else(|| format!("Main window '{}' is not available", MAIN_WINDOW))
+}
+
+fn state_path(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::paths::data_dir(app).map(|dir| dir.join(STATE_FILE))
+}
+
+pub(crate) fn load(app: &AppHandle) -> Option<WindowState> {
+    let raw = fs::read_to_string(state_path(app).ok()?).ok()?;
+    serde_json::from_str(&raw)
+        .inspect_err(|err| log::warn!("Ignoring unreadable {}: {}", STATE_FILE, err))
+        .ok()
+}
+
+fn save(window: &Window) -> Result<(), String> {
+    // A minimized window reports a meaningless position (-32000 on Windows).
+    if window.is_minimized().map_err(|err| err.to_string())? {
+        return Ok(());
+    }
+    let app = window.app_handle();
+    let maximized = window.is_maximized().map_err(|err| err.to_string())?;
+    let state = match (maximized, load(app)) {
+        (true, Some(previous)) => WindowState {
+            maximized: true,
+            ..previous
+        },
+        _ => {
+            let size = window.outer_size().map_err(|err| err.to_string())?;
+            let position = window.outer_position().map_err(|err| err.to_string())?;
+            WindowState {
+                width: size.width,
+                height: size.height,
+                x: position.x,
+                y: position.y,
+                maximized,
+            }
+        }
+    };
+
+    let path = state_path(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(&state).map_err(|err| err.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, raw).map_err(|err| err.to_string())?;
+    fs::rename(&tmp, &path).map_err(|err| err.to_string())
+}
+
+/// Hooked into the builder's `on_window_event`.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != MAIN_WINDOW {
+        return;
+    }
+    match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+            let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+            let window = window.clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(SAVE_DELAY).await;
+                if GENERATION.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                if let Err(err) = save(&window) {
+                    log::warn!("Failed to save window state: {}", err);
+                }
+            });
+        }
+        WindowEvent::CloseRequested { .. } => {
+            // Cancel any pending save; this one is final.
+            GENERATION.fetch_add(1, Ordering::SeqCst);
+            if let Err(err) = save(window) {
+                log::warn!("Failed to save window state: {}", err);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether enough of the window's top edge is on `monitor` to grab it.
+fn title_bar_visible(state: &WindowState, monitor: &Monitor) -> bool {
+    let area = monitor.work_area();
+    let (left, top) = (area.position.x, area.position.y);
+    let right = left + area.size.width as i32;
+    let bottom = top + area.size.height as i32;
+    let visible_width = (state.x + state.width as i32).min(right) - state.x.max(left);
+    visible_width >= MIN_VISIBLE && state.y >= top && state.y + MIN_VISIBLE / 2 <= bottom
+}
+
+/// Moves a saved geometry onto the current monitor layout: kept as is when
+/// its title bar is reachable, otherwise shrunk to fit and centered on the
+/// primary monitor (e.g. after an external display was disconnected).
+fn fit_to_monitors(
+    mut state: WindowState,
+    monitors: &[Monitor],
+    primary: Option<Monitor>,
+) -> WindowState {
+    if monitors
+        .iter()
+        .any(|monitor| title_bar_visible(&state, monitor))
+    {
+        return state;
+    }
+    let Some(monitor) = primary.or_else(|| monitors.first().cloned()) else {
+        return state;
+    };
+    let area = monitor.work_area();
+    state.width = state.width.min(area.size.width);
+    state.height = state.height.min(area.size.height);
+    state.x = area.position.x + (area.size.width - state.width) as i32 / 2;
+    state.y = area.position.y + (area.size.height - state.height) as i32 / 2;
+    state
+}
+
+fn apply(window: &WebviewWindow, state: WindowState) -> tauri::Result<()> {
+    let monitors = window.available_monitors()?;
+    let state = fit_to_monitors(state, &monitors, window.primary_monitor()?);
+    window.set_size(PhysicalSize::new(state.width, state.height))?;
+    window.set_position(PhysicalPosition::new(state.x, state.y))?;
+    if state.maximized {
+        window.maximize()?;
+    }
+    Ok(())
+}
+
+/// Applies the saved geometry, if any, and shows the main window. Call from
+/// `setup`.
+pub fn restore(app: &AppHandle) {
+    let window = match main_window(app) {
+        Ok(window) => window,
+        Err(err) => {
+            log::warn!("Not restoring window state: {}", err);
+            return;
+        }
+    };
+    if let Some(state) = load(app) {
+        if let Err(err) = apply(&window, state) {
+            log::warn!("Failed to restore window state: {}", err);
+        }
+    }
+    if let Err(err) = window.show() {
+        log::warn!("Failed to show main window: {}", err);
+    }
+}
+
+/// Forgets the saved geometry, so the next launch uses the default size,
+/// centered.
+#[tauri::command]
+pub fn reset_window_state(app: AppHandle) -> Result<(), String> {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    match fs::remove_file(state_path(&app)?) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::main_window;
+
+    #[test]
+    fn main_window_missing_is_an_error() {
+        let app = tauri::test::mock_app();
+        let err = main_window(app.handle()).unwrap_err();
+        assert_eq!(err, "Main window 'main' is not available");
+    }
+}
@@ -1,4 +1,5 @@
 import { isTauriEnvironment, getDatabase } from './db'
+import { getDataDir } from './dataDir'
 
 export interface AttachmentCleanupResult {
   deletedRows: number
@@ -33,7 +34,7 @@ export async function cleanupOrphanChatAttachments(): Promise<AttachmentCleanupR
     )
 
     const { exists, readDir, remove, stat } = await import('@tauri-apps/plugin-fs')
-    const { appDataDir, join } = await import('@tauri-apps/api/path')
+    const { join } = await import('@tauri-apps/api/path')
 
     for (const attachment of attachments) {
       if (!attachment.file_path) continue
@@ -47,7 +48,7 @@ export async function cleanupOrphanChatAttachments(): Promise<AttachmentCleanupR
       }
     }
 
-    const appData = await appDataDir()
+    const appData = await getDataDir()
     const attachmentsRoot = await join(appData, 'chat_attachments')
     const rootExists = await exists(attachmentsRoot)
     if (!rootExists) return result
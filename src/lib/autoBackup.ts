@@ -6,6 +6,7 @@
  */
 
 import { exportDatabase, isTauriEnvironment, type DatabaseBackup } from './db'
+import { getDataDir } from './dataDir'
 
 // ============================================================================
 // Types
@@ -193,10 +194,10 @@ export function setBackupSessionPassword(password: string | null): void {
  */
 async function isValidBackupPath(path: string): Promise<boolean> {
   try {
-    const { appDataDir, normalize } = await import('@tauri-apps/api/path')
+    const { normalize } = await import('@tauri-apps/api/path')
 
     const [appData, normalizedPath] = await Promise.all([
-      appDataDir(),
+      getDataDir(),
       normalize(path),
     ])
 
@@ -239,8 +240,8 @@ async function getBackupDirectory(): Promise<string> {
   }
 
   // Default: AppData/JurisDesk/backups
-  const { appDataDir, join } = await import('@tauri-apps/api/path')
-  const appData = await appDataDir()
+  const { join } = await import('@tauri-apps/api/path')
+  const appData = await getDataDir()
   const backupDir = await join(appData, 'backups')
   await ensureDirectory(backupDir)
   return backupDir
@@ -0,0 +1,40 @@
+/**
+ * Localização dos dados do app.
+ *
+ * O backend pode mover o diretório de dados para outro volume
+ * (`relocate_data_directory`), e o banco principal vai junto. Use estas
+ * funções no lugar de `appDataDir()` e de `'sqlite:jurisdesk.db'`.
+ */
+
+const DEFAULT_DATABASE_URL = 'sqlite:jurisdesk.db'
+
+/**
+ * Diretório de dados atual (o movido, ou o AppData padrão)
+ * @returns Caminho absoluto do diretório
+ */
+export async function getDataDir(): Promise<string> {
+  try {
+    const { invoke } = await import('@tauri-apps/api/core')
+    const dir = await invoke<string>('get_data_dir')
+    if (dir) return dir
+  } catch {
+    // Backend sem o comando (ou testes): usa o diretório padrão.
+  }
+  const { appDataDir } = await import('@tauri-apps/api/path')
+  return appDataDir()
+}
+
+/**
+ * URL de conexão do banco principal para `Database.load`
+ * @returns `sqlite:jurisdesk.db`, ou `sqlite:<caminho absoluto>` se os dados foram movidos
+ */
+export async function getDatabaseUrl(): Promise<string> {
+  try {
+    const { invoke } = await import('@tauri-apps/api/core')
+    const url = await invoke<string>('get_database_url')
+    if (url) return url
+  } catch {
+    // Backend sem o comando (ou testes): usa o local padrão.
+  }
+  return DEFAULT_DATABASE_URL
+}
@@ -407,6 +407,13 @@ describe('db.ts - Database Core Functionality', () => {
       await expect(exportTableAsCSV('DROP TABLE clients')).rejects.toThrow('Tabela não permitida')
     })
 
+    it('should validate table names for CSV file export', async () => {
+      const { exportTableToCSVFile } = await import('./db')
+
+      await expect(exportTableToCSVFile('invalid_table', '/tmp/out.csv')).rejects.toThrow('Tabela não permitida')
+      await expect(exportTableToCSVFile('DROP TABLE clients', '/tmp/out.csv')).rejects.toThrow('Tabela não permitida')
+    })
+
     it('should handle empty tables for CSV export', async () => {
       const { exportTableAsCSV } = await import('./db')
       
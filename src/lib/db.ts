@@ -2,6 +2,7 @@ import Database from '@tauri-apps/plugin-sql'
 import type { Client, Case, Document, Deadline, ChatSession, ChatMessage, ChatAttachment, Settings, DocumentFolder, ActivityLog, AIUsageLog } from '@/types'
 import { isSensitiveSettingKey } from './securityConstants'
 import { sanitizeImportedAttachmentPath, sanitizeImportedDocumentPath } from './pathSecurity'
+import { getDatabaseUrl } from './dataDir'
 
 let db: Database | null = null
 let dbInitPromise: Promise<Database> | null = null
@@ -54,7 +55,7 @@ export async function getDatabase(): Promise<Database> {
   // Prevent concurrent initialization (App boot triggers multiple fetch* in parallel).
   if (!dbInitPromise) {
     dbInitPromise = (async () => {
-      const database = await Database.load('sqlite:jurisdesk.db')
+      const database = await Database.load(await getDatabaseUrl())
       db = database
       await applyPragmas(database)
       await initializeTables()
@@ -1394,6 +1395,31 @@ export async function exportTableAsCSV(tableName: string): Promise<string> {
   return csvLines.join('\n')
 }
 
+/**
+ * Exporta uma tabela como CSV direto para um arquivo, pelo backend
+ * @param tableName - Nome da tabela ('clients', 'cases', 'documents', 'deadlines')
+ * @param destPath - Caminho retornado por `prompt_save_path`
+ * @returns Número de linhas escritas
+ * @throws Error se tabela não for permitida
+ * @example
+ * const path = await invoke<string | null>('prompt_save_path', { defaultName: 'clients.csv', filters: [['CSV', ['csv']]] })
+ * if (path) await exportTableToCSVFile('clients', path)
+ */
+export async function exportTableToCSVFile(tableName: string, destPath: string): Promise<number> {
+  const allowedTables = ['clients', 'cases', 'documents', 'deadlines']
+  if (!allowedTables.includes(tableName)) {
+    throw new Error(`Tabela não permitida: ${tableName}`)
+  }
+
+  const { invoke } = await import('@tauri-apps/api/core')
+  return invoke<number>('export_query_csv', {
+    dbPath: 'sqlite:jurisdesk.db',
+    sql: `SELECT * FROM ${tableName}`,
+    params: [],
+    destPath,
+  })
+}
+
 /**
  * Retorna estatísticas de contagem de registros do banco
  * @returns Objeto com contagem de cada tabela principal
@@ -1,4 +1,5 @@
 import { isTauriEnvironment, getDatabase } from './db'
+import { getDataDir } from './dataDir'
 
 export interface DocumentCleanupResult {
   removedFiles: number
@@ -26,10 +27,10 @@ export async function cleanupOrphanClientDocuments(): Promise<DocumentCleanupRes
         .filter((path): path is string => Boolean(path))
     )
 
-    const { appDataDir, join } = await import('@tauri-apps/api/path')
+    const { join } = await import('@tauri-apps/api/path')
     const { exists, readDir, remove, stat } = await import('@tauri-apps/plugin-fs')
 
-    const appData = await appDataDir()
+    const appData = await getDataDir()
     const documentsRoot = await join(appData, 'documents')
 
     const rootExists = await exists(documentsRoot)
@@ -1,4 +1,5 @@
 import { isTauriEnvironment } from './db'
+import { getDataDir } from './dataDir'
 import { isManagedDocumentPath } from './pathSecurity'
 
 const DOCUMENTS_ROOT_DIR = 'documents'
@@ -35,8 +36,8 @@ export async function ensureClientDocumentDir(clientId: number): Promise<string>
     throw new Error('Document storage is only available in the Tauri environment.')
   }
 
-  const { appDataDir, join } = await import('@tauri-apps/api/path')
-  const appData = await appDataDir()
+  const { join } = await import('@tauri-apps/api/path')
+  const appData = await getDataDir()
   const clientDir = await join(appData, DOCUMENTS_ROOT_DIR, String(clientId))
   await ensureDirectory(clientDir)
   return clientDir
@@ -97,10 +98,10 @@ export async function removeClientDocumentDirIfEmpty(clientId: number): Promise<
   if (!isTauriEnvironment()) return
 
   try {
-    const { appDataDir, join } = await import('@tauri-apps/api/path')
+    const { join } = await import('@tauri-apps/api/path')
     const { exists, readDir, remove } = await import('@tauri-apps/plugin-fs')
 
-    const appData = await appDataDir()
+    const appData = await getDataDir()
     const clientDir = await join(appData, DOCUMENTS_ROOT_DIR, String(clientId))
     const dirExists = await exists(clientDir)
     if (!dirExists) return
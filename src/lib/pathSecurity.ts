@@ -1,3 +1,5 @@
+import { getDataDir } from './dataDir'
+
 const DOCUMENTS_ROOT_DIR = 'documents'
 const CHAT_ATTACHMENTS_ROOT_DIR = 'chat_attachments'
 
@@ -35,8 +37,8 @@ export async function getStorageRoots(): Promise<StorageRoots> {
 
   if (!storageRootsPromise) {
     storageRootsPromise = (async () => {
-      const { appDataDir, join } = await import('@tauri-apps/api/path')
-      const appDataRoot = await appDataDir()
+      const { join } = await import('@tauri-apps/api/path')
+      const appDataRoot = await getDataDir()
       const [documentsRoot, attachmentsRoot] = await Promise.all([
         join(appDataRoot, DOCUMENTS_ROOT_DIR),
         join(appDataRoot, CHAT_ATTACHMENTS_ROOT_DIR),
@@ -30,6 +30,7 @@ import {
   deleteChatAttachmentsBySession,
 } from '@/lib/db'
 import { isManagedAttachmentPath } from '@/lib/pathSecurity'
+import { getDataDir } from '@/lib/dataDir'
 import { useChatStore } from '@/stores/chatStore'
 import { useSettingsStore } from '@/stores/settingsStore'
 import { useDocumentStore } from '@/stores/documentStore'
@@ -535,9 +536,9 @@ export default function Assistant() {
     uploadedFiles.reduce((sum, f) => sum + f.size, 0)
 
   const ensureAttachmentDirectory = async (sessionId: number): Promise<string> => {
-    const { appDataDir, join } = await import('@tauri-apps/api/path')
+    const { join } = await import('@tauri-apps/api/path')
     const { mkdir, exists } = await import('@tauri-apps/plugin-fs')
-    const appData = await appDataDir()
+    const appData = await getDataDir()
     const attachmentsDir = await join(appData, 'chat_attachments', String(sessionId))
     const dirExists = await exists(attachmentsDir)
     if (!dirExists) {
@@ -38,7 +38,7 @@ import {
   isTauriEnvironment,
   exportDatabase,
   importDatabase,
-  exportTableAsCSV,
+  exportTableToCSVFile,
   getDatabaseStats,
   getDatabaseHealth,
   DATABASE_CHANGED_EVENT,
@@ -509,23 +509,20 @@ export default function Settings() {
     setExportLoading(tableName)
     setBackupMessage(null)
     try {
-      const csv = await exportTableAsCSV(tableName)
+      const { invoke } = await import('@tauri-apps/api/core')
 
-      if (!csv) {
-        setBackupMessage({ type: 'error', text: `Tabela ${tableName} estÃ¡ vazia.` })
-        return
-      }
-
-      const { save } = await import('@tauri-apps/plugin-dialog')
-      const { writeTextFile } = await import('@tauri-apps/plugin-fs')
-
-      const savePath = await save({
-        defaultPath: `${tableName}_${new Date().toISOString().split('T')[0]}.csv`,
-        filters: [{ name: 'CSV', extensions: ['csv'] }],
+      // The backend only writes to absolute paths it handed out itself.
+      const savePath = await invoke<string | null>('prompt_save_path', {
+        defaultName: `${tableName}_${new Date().toISOString().split('T')[0]}.csv`,
+        filters: [['CSV', ['csv']]],
       })
 
       if (savePath) {
-        await writeTextFile(savePath, csv)
+        const rows = await exportTableToCSVFile(tableName, savePath)
+        if (rows === 0) {
+          setBackupMessage({ type: 'error', text: `Tabela ${tableName} estÃ¡ vazia.` })
+          return
+        }
         setBackupMessage({ type: 'success', text: `${tableName} exportado com sucesso!` })
       }
     } catch (err) {